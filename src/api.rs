@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, StatusCode, Url};
+
+use crate::retry;
+
+/// Requests that take longer are abandoned and tried again
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Settings shared by the embedders that call an HTTP API.
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// URL the endpoint's path is appended to
+    pub base_url: String,
+    /// Model to ask for, or the backend's default when `None`
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    /// Size to shorten the vectors to, for models that support it
+    pub dimensions: Option<u64>,
+    /// Number of texts sent per request
+    pub batch_size: usize,
+    /// Number of requests in flight at once, each with a batch of texts
+    pub concurrency: usize,
+    /// Number of times to try a request
+    pub max_attempts: u32,
+}
+
+impl ApiConfig {
+    /// The base URL with the endpoint's path segments appended, keeping any query like Azure's `api-version`.
+    pub(crate) fn endpoint(&self, path: &[&str]) -> anyhow::Result<Url> {
+        let mut url = Url::parse(&self.base_url).map_err(|e| anyhow::anyhow!("Invalid API URL {}: {}", self.base_url, e))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid API URL {}", self.base_url))?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
+    }
+
+    /// Number of texts to embed at once, enough for a batch per request in flight.
+    pub(crate) fn texts_per_call(&self) -> usize {
+        self.batch_size * self.concurrency
+    }
+}
+
+pub(crate) fn client() -> anyhow::Result<reqwest::Client> {
+    Ok(reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?)
+}
+
+/// Send a request and parse its JSON response.
+///
+/// Requests rejected for exceeding a rate limit, failing on the server or not reaching it are tried again,
+/// after the wait given by the `Retry-After` header or a jittered exponential backoff.
+pub(crate) async fn send(request: RequestBuilder, max_attempts: u32) -> anyhow::Result<serde_json::Value> {
+    let mut attempt = 1;
+    loop {
+        let attempt_request = request.try_clone().expect("requests with a JSON body can be cloned");
+        let (error, retry_after) = match attempt_request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(response.json().await?),
+            Ok(response) => {
+                let status = response.status();
+                let url = response.url().clone();
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                let error = anyhow::anyhow!("{} returned {}: {}", url, status, error_message(&response.text().await?));
+                if !is_retryable(status) {
+                    return Err(error);
+                }
+                (error, retry_after)
+            }
+            Err(e) => (anyhow::Error::from(e), None),
+        };
+        if attempt >= max_attempts {
+            return Err(anyhow::anyhow!("Embedding request failed after {} attempt(s): {:#}", attempt, error));
+        }
+        let delay = retry_after.unwrap_or_else(|| retry::backoff(attempt));
+        log::warn!(
+            "Embedding request attempt {} of {} failed: {:#}; retrying in {:.1}s",
+            attempt,
+            max_attempts,
+            error,
+            delay.as_secs_f32()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Embed the texts in batches of `batch_size`, with up to `concurrency` requests at once, keeping their order.
+pub(crate) async fn embed_batches<'t, F, Fut>(texts: &'t [&'t str], config: &ApiConfig, request: F) -> anyhow::Result<Vec<Vec<f32>>>
+where
+    F: Fn(&'t [&'t str]) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<Vec<f32>>>>,
+{
+    // Build the requests up front: a stream holding the closure trips up the compiler's check that the future is Send
+    let requests = texts.chunks(config.batch_size).map(request).collect::<Vec<_>>();
+    let batches = stream::iter(requests)
+        .buffered(config.concurrency)
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(batches.into_iter().flatten().collect())
+}
+
+/// Parse a list of vectors, checking there is one for every text.
+pub(crate) fn parse_vectors(vectors: &serde_json::Value, count: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+    let vectors = serde_json::from_value::<Vec<Vec<f32>>>(vectors.clone())
+        .map_err(|e| anyhow::anyhow!("Unexpected embeddings in response: {}", e))?;
+    if vectors.len() != count {
+        return Err(anyhow::anyhow!("Response has {} embeddings, expected {}", vectors.len(), count));
+    }
+    Ok(vectors)
+}
+
+/// Rate limits and server errors are worth trying again, other errors fail the same way every time.
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT || status.is_server_error()
+}
+
+/// The message of an error response: `error.message` for OpenAI, `error` for Ollama and TEI, or the whole body.
+fn error_message(body: &str) -> String {
+    let error = serde_json::from_str::<serde_json::Value>(body).unwrap_or_default();
+    match (error["error"]["message"].as_str(), error["error"].as_str()) {
+        (Some(message), _) | (None, Some(message)) => message.to_string(),
+        (None, None) => body.trim().to_string(),
+    }
+}
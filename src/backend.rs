@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use fastembed::EmbeddingModel;
+use vectordb::embedding::{self, Backend};
+use vectordb::{ollama, openai, tei};
+use vectordb::{ApiConfig, Embedder, FastEmbedder, ModelCache, OllamaEmbedder, OnnxEmbedder, OnnxOptions, OpenAiEmbedder, TeiEmbedder};
+
+use crate::cli::EmbedderArgs;
+
+/// Load the embedder of the backend, with the backend's default model when none is given.
+pub async fn load(backend: Backend, args: &EmbedderArgs, model: Option<String>, cache: &ModelCache) -> anyhow::Result<Box<dyn Embedder>> {
+    check_args(backend, args)?;
+    let config = |default_url: &str| ApiConfig {
+        base_url: args.embedding_url.clone().unwrap_or_else(|| default_url.to_string()),
+        model: model.clone(),
+        api_key: args.embedding_api_key.clone(),
+        dimensions: args.dimensions,
+        batch_size: args.embedding_batch_size,
+        concurrency: args.embedding_concurrency,
+        max_attempts: args.embedding_max_attempts,
+    };
+    Ok(match backend {
+        Backend::Fastembed => {
+            let name = model.unwrap_or_else(|| embedding::model_name(&EmbeddingModel::AllMiniLML6V2));
+            let model = embedding::parse_model(&name).map_err(|e| anyhow::anyhow!("Invalid model {}: {}", name, e))?;
+            Box::new(FastEmbedder::new(model, cache)?)
+        }
+        Backend::Onnx => {
+            let dir = args
+                .model_path
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("The onnx backend needs the directory of the model, see --model-path"))?;
+            eprintln!("Loading ONNX model from {}", dir.display());
+            let embedder = OnnxEmbedder::new(OnnxOptions {
+                dir,
+                name: model,
+                pooling: args.pooling,
+                normalize: args.normalize,
+                max_length: None,
+            })?;
+            eprintln!(
+                "Pooling: {:?}, {}",
+                embedder.pooling(),
+                if embedder.normalizes() { "normalized" } else { "not normalized" }
+            );
+            Box::new(embedder)
+        }
+        Backend::Openai => {
+            let mut config = config(openai::DEFAULT_BASE_URL);
+            config.api_key = config.api_key.or_else(|| std::env::var("OPENAI_API_KEY").ok());
+            eprintln!("Connecting to embedding API at {}", config.base_url);
+            Box::new(OpenAiEmbedder::connect(config).await?)
+        }
+        Backend::Ollama => {
+            let config = config(ollama::DEFAULT_BASE_URL);
+            eprintln!("Connecting to Ollama at {}", config.base_url);
+            Box::new(OllamaEmbedder::connect(config).await?)
+        }
+        Backend::Tei => {
+            let config = config(tei::DEFAULT_BASE_URL);
+            eprintln!("Connecting to Text Embeddings Inference at {}", config.base_url);
+            Box::new(TeiEmbedder::connect(config).await?)
+        }
+    })
+}
+
+/// Stand in for an embedding API without sending it a request, for dry runs that don't embed.
+///
+/// Connecting sends a probe request, which the provider may bill, only to learn the size of the vectors.
+pub fn unconnected(backend: Backend, args: &EmbedderArgs, model: Option<String>) -> anyhow::Result<Box<dyn Embedder>> {
+    check_args(backend, args)?;
+    let model = match backend {
+        Backend::Openai => model.unwrap_or_else(|| openai::DEFAULT_MODEL.to_string()),
+        Backend::Ollama => model.unwrap_or_else(|| ollama::DEFAULT_MODEL.to_string()),
+        // The server names its model
+        _ => model.unwrap_or_else(|| String::from("unknown")),
+    };
+    Ok(Box::new(Unconnected { backend, model }))
+}
+
+fn check_args(backend: Backend, args: &EmbedderArgs) -> anyhow::Result<()> {
+    if args.embedding_url.is_some() && !backend.is_api() {
+        return Err(anyhow::anyhow!("--embedding-url only applies to embedding APIs, see --backend"));
+    }
+    if args.dimensions.is_some() && backend != Backend::Openai {
+        return Err(anyhow::anyhow!("--dimensions only applies to the openai backend"));
+    }
+    if (args.model_path.is_some() || args.pooling.is_some() || args.normalize.is_some()) && backend != Backend::Onnx {
+        return Err(anyhow::anyhow!("--model-path, --pooling and --normalize only apply to the onnx backend"));
+    }
+    Ok(())
+}
+
+/// An embedding API that was not connected to, so the size of its vectors is unknown.
+struct Unconnected {
+    backend: Backend,
+    model: String,
+}
+
+#[async_trait]
+impl Embedder for Unconnected {
+    fn backend(&self) -> String {
+        self.backend.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn dimensions(&self) -> u64 {
+        0
+    }
+
+    async fn embed(&self, _texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        Err(anyhow::anyhow!("The embedding API was not connected to"))
+    }
+}
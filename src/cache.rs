@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+
+use fastembed::{EmbeddingModel, TextEmbedding};
+use walkdir::WalkDir;
+
+use crate::embedding;
+
+/// Default directory models are downloaded to, relative to the working directory like fastembed's own default
+pub const DEFAULT_DIR: &str = ".fastembed_cache";
+
+/// Files fastembed reads from a model's repository besides its ONNX file
+const TOKENIZER_FILES: [&str; 4] = ["tokenizer.json", "config.json", "special_tokens_map.json", "tokenizer_config.json"];
+
+/// Where fastembed models are downloaded to, and whether they may be downloaded at all.
+///
+/// The directory uses the Hugging Face hub layout: a `models--<org>--<name>` directory per repository, whose
+/// `refs/main` names the snapshot holding the files.
+#[derive(Debug, Clone)]
+pub struct ModelCache {
+    pub dir: PathBuf,
+    /// Fail instead of downloading models that are not cached
+    pub offline: bool,
+}
+
+impl Default for ModelCache {
+    fn default() -> Self {
+        ModelCache {
+            dir: PathBuf::from(DEFAULT_DIR),
+            offline: false,
+        }
+    }
+}
+
+/// A model repository in the cache.
+#[derive(Debug)]
+pub struct CacheEntry {
+    /// Hugging Face repository, like `Qdrant/all-MiniLM-L6-v2-onnx`
+    pub repository: String,
+    pub path: PathBuf,
+    /// Bytes of all files in the repository's directory
+    pub size: u64,
+    /// Names of the models whose files are all in the repository
+    pub models: Vec<String>,
+    /// Whether any model this version of fastembed supports comes from the repository
+    pub known: bool,
+}
+
+/// A model fastembed can download, with the files it needs.
+struct KnownModel {
+    name: String,
+    repository: String,
+    files: Vec<String>,
+}
+
+impl ModelCache {
+    pub fn new(dir: impl Into<PathBuf>, offline: bool) -> Self {
+        ModelCache { dir: dir.into(), offline }
+    }
+
+    /// Whether every file of the model is cached, so loading it needs no network access.
+    pub fn is_cached(&self, model: &EmbeddingModel) -> bool {
+        let known = dense_model(model);
+        self.has_files(&known.repository, &known.files)
+    }
+
+    /// In offline mode, fail with instructions unless the model is cached.
+    pub fn check(&self, name: &str, cached: bool) -> anyhow::Result<()> {
+        if self.offline && !cached {
+            return Err(anyhow::anyhow!(
+                "Model {} is not in the model cache {} and --offline forbids downloading it; run `models download {}` on a machine with network access and copy the cache directory here",
+                name,
+                self.dir.display(),
+                name
+            ));
+        }
+        Ok(())
+    }
+
+    /// The model repositories in the cache, sorted by name.
+    pub fn entries(&self) -> anyhow::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        if !self.dir.is_dir() {
+            return Ok(entries);
+        }
+        let known = known_models();
+        for dir in std::fs::read_dir(&self.dir)? {
+            let path = dir?.path();
+            let Some(repository) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("models--"))
+                .map(|name| name.replace("--", "/"))
+            else {
+                continue;
+            };
+            let models = known
+                .iter()
+                .filter(|model| model.repository == repository && self.has_files(&model.repository, &model.files))
+                .map(|model| model.name.clone())
+                .collect();
+            entries.push(CacheEntry {
+                known: known.iter().any(|model| model.repository == repository),
+                size: directory_size(&path),
+                repository,
+                path,
+                models,
+            });
+        }
+        entries.sort_by(|a, b| a.repository.cmp(&b.repository));
+        Ok(entries)
+    }
+
+    fn repository_dir(&self, repository: &str) -> PathBuf {
+        self.dir.join(format!("models--{}", repository.replace('/', "--")))
+    }
+
+    /// Whether the snapshot `refs/main` points to has all the files, which is where fastembed looks before downloading.
+    fn has_files(&self, repository: &str, files: &[String]) -> bool {
+        let dir = self.repository_dir(repository);
+        let Ok(commit) = std::fs::read_to_string(dir.join("refs").join("main")) else {
+            return false;
+        };
+        let snapshot = dir.join("snapshots").join(commit.trim());
+        files.iter().all(|file| snapshot.join(file).is_file())
+    }
+}
+
+fn dense_model(model: &EmbeddingModel) -> KnownModel {
+    let info = embedding::model_info(model);
+    let mut files = vec![info.model_file];
+    files.extend(TOKENIZER_FILES.iter().map(|file| file.to_string()));
+    // The only model whose weights are too large for a single ONNX file
+    if *model == EmbeddingModel::MultilingualE5Large {
+        files.push(String::from("model.onnx_data"));
+    }
+    KnownModel {
+        name: embedding::model_name(model),
+        repository: info.model_code,
+        files,
+    }
+}
+
+fn known_models() -> Vec<KnownModel> {
+    TextEmbedding::list_supported_models().into_iter().map(|info| dense_model(&info.model)).collect()
+}
+
+/// Total size of the files under the directory, counting files that snapshots link to only once.
+fn directory_size(path: &std::path::Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
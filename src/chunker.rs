@@ -0,0 +1,372 @@
+use clap::ValueEnum;
+use serde_json::json;
+use text_splitter::{Characters, ChunkCapacity, ChunkConfig, ChunkSizer, MarkdownSplitter, TextSplitter};
+use tiktoken_rs::{cl100k_base, o200k_base, p50k_base, CoreBPE};
+use tokenizers::Tokenizer;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::document::Document;
+use crate::pipeline::Chunker;
+
+/// How documents are split into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChunkerKind {
+    /// Up to `--chunk-size` tokens per chunk
+    Tokens,
+    /// Up to `--chunk-size` tokens per chunk, split along Markdown structure even for PDFs
+    Markdown,
+    /// Up to `--chunk-size` characters per chunk
+    Characters,
+    /// `--chunk-size` sentences per chunk
+    Sentences,
+    /// `--chunk-size` paragraphs per chunk
+    Paragraphs,
+    /// `--chunk-size` pages per chunk
+    Pages,
+}
+
+impl ChunkerKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ChunkerKind::Tokens => "tokens",
+            ChunkerKind::Markdown => "markdown",
+            ChunkerKind::Characters => "characters",
+            ChunkerKind::Sentences => "sentences",
+            ChunkerKind::Paragraphs => "paragraphs",
+            ChunkerKind::Pages => "pages",
+        }
+    }
+
+    /// Whether chunk sizes are measured with a sizer, rather than counted in whole units.
+    fn is_sized(&self) -> bool {
+        matches!(self, ChunkerKind::Tokens | ChunkerKind::Markdown | ChunkerKind::Characters)
+    }
+
+    /// Whether chunk sizes are measured with `--tokenizer`.
+    fn uses_tokenizer(&self) -> bool {
+        matches!(self, ChunkerKind::Tokens | ChunkerKind::Markdown)
+    }
+}
+
+/// What the tokens and markdown chunkers measure chunk sizes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TokenizerKind {
+    /// OpenAI's cl100k_base encoding
+    Cl100k,
+    /// OpenAI's o200k_base encoding
+    O200k,
+    /// OpenAI's p50k_base encoding
+    P50k,
+    /// Unicode characters
+    Characters,
+    /// The embedding model's own HuggingFace tokenizer
+    Model,
+}
+
+impl TokenizerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenizerKind::Cl100k => "cl100k_base",
+            TokenizerKind::O200k => "o200k_base",
+            TokenizerKind::P50k => "p50k_base",
+            TokenizerKind::Characters => "characters",
+            TokenizerKind::Model => "model",
+        }
+    }
+}
+
+/// Counts tokens the way chunks are measured with `--tokenizer`, for models without a local tokenizer.
+pub enum TokenCounter {
+    Bpe(CoreBPE),
+    Characters,
+}
+
+impl TokenCounter {
+    /// Create the counter; `--tokenizer model` has no counter of its own, use the model's tokenizer instead.
+    pub fn new(kind: TokenizerKind) -> anyhow::Result<Self> {
+        Ok(match kind {
+            TokenizerKind::Cl100k => TokenCounter::Bpe(cl100k_base()?),
+            TokenizerKind::O200k => TokenCounter::Bpe(o200k_base()?),
+            TokenizerKind::P50k => TokenCounter::Bpe(p50k_base()?),
+            TokenizerKind::Characters => TokenCounter::Characters,
+            TokenizerKind::Model => return Err(anyhow::anyhow!("--tokenizer model counts with the embedding model's tokenizer")),
+        })
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Bpe(bpe) => bpe.encode_ordinary(text).len(),
+            TokenCounter::Characters => text.chars().count(),
+        }
+    }
+}
+
+/// Chunking parameters given on the command line.
+#[derive(Debug, Clone)]
+pub struct ChunkSettings {
+    pub kind: ChunkerKind,
+    pub tokenizer: TokenizerKind,
+    pub min_size: Option<usize>,
+    pub max_size: usize,
+    pub overlap: usize,
+}
+
+impl ChunkSettings {
+    /// Check the parameters before any work is done.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.overlap >= self.max_size {
+            return Err(anyhow::anyhow!("--chunk-overlap must be smaller than --chunk-size"));
+        }
+        if let Some(min_size) = self.min_size {
+            if !self.kind.is_sized() {
+                return Err(anyhow::anyhow!("--min-chunk-size only applies to the tokens, markdown and characters chunkers"));
+            }
+            if min_size > self.max_size {
+                return Err(anyhow::anyhow!("--min-chunk-size must not be larger than --chunk-size"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Limit the chunk size to the embedding model's maximum input length, when chunks are measured
+    /// with the model's own tokenizer. Returns whether the size was reduced.
+    pub fn clamp_to_model(&mut self, max_tokens: usize) -> bool {
+        if !self.kind.uses_tokenizer() || self.tokenizer != TokenizerKind::Model || self.max_size <= max_tokens {
+            return false;
+        }
+        self.max_size = max_tokens;
+        self.min_size = self.min_size.map(|min_size| min_size.min(max_tokens));
+        true
+    }
+
+    /// The parameters recorded in the payload of every chunk.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut settings = json!({
+            "strategy": self.kind.name(),
+            "max_size": self.max_size,
+            "overlap": self.overlap,
+        });
+        if let Some(min_size) = self.min_size {
+            settings["min_size"] = min_size.into();
+        }
+        if self.kind.uses_tokenizer() {
+            settings["tokenizer"] = self.tokenizer.name().into();
+        }
+        settings
+    }
+}
+
+/// A `text_splitter` splitter, independent of the sizer it measures chunks with.
+pub trait Split: Send + Sync {
+    fn split<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)>;
+}
+
+impl<S: ChunkSizer + Send + Sync> Split for TextSplitter<S> {
+    fn split<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        self.chunk_indices(text).collect()
+    }
+}
+
+impl<S: ChunkSizer + Send + Sync> Split for MarkdownSplitter<S> {
+    fn split<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        self.chunk_indices(text).collect()
+    }
+}
+
+/// Splits a document into chunks, returning each chunk with its byte offset into the document's text.
+///
+/// The sized chunkers split Markdown documents with a `MarkdownSplitter`, so chunks respect headings, lists and code blocks.
+/// Each variant keeps the settings it was created with, to record them in the payload.
+pub enum TextChunker {
+    Sized { text: Box<dyn Split>, markdown: Box<dyn Split>, always_markdown: bool, settings: serde_json::Value },
+    Sentences { size: usize, overlap: usize, settings: serde_json::Value },
+    Paragraphs { size: usize, overlap: usize, settings: serde_json::Value },
+    Pages { size: usize, overlap: usize, settings: serde_json::Value },
+}
+
+impl TextChunker {
+    /// Create the chunker; `model_tokenizer` is the embedding model's tokenizer, required by `--tokenizer model`.
+    pub fn new(settings: &ChunkSettings, model_tokenizer: Option<&Tokenizer>) -> anyhow::Result<Self> {
+        settings.validate()?;
+        let ChunkSettings { kind, tokenizer, min_size, max_size, overlap } = *settings;
+        let settings = settings.to_json();
+
+        let capacity = match min_size {
+            Some(min_size) => ChunkCapacity::new(min_size).with_max(max_size)?,
+            None => ChunkCapacity::new(max_size),
+        };
+        let always_markdown = kind == ChunkerKind::Markdown;
+
+        Ok(match (kind, tokenizer) {
+            (ChunkerKind::Characters, _) | (ChunkerKind::Tokens | ChunkerKind::Markdown, TokenizerKind::Characters) => {
+                sized(capacity, Characters, overlap, always_markdown, settings)?
+            }
+            (ChunkerKind::Tokens | ChunkerKind::Markdown, TokenizerKind::Cl100k) => {
+                sized(capacity, cl100k_base()?, overlap, always_markdown, settings)?
+            }
+            (ChunkerKind::Tokens | ChunkerKind::Markdown, TokenizerKind::O200k) => {
+                sized(capacity, o200k_base()?, overlap, always_markdown, settings)?
+            }
+            (ChunkerKind::Tokens | ChunkerKind::Markdown, TokenizerKind::P50k) => {
+                sized(capacity, p50k_base()?, overlap, always_markdown, settings)?
+            }
+            (ChunkerKind::Tokens | ChunkerKind::Markdown, TokenizerKind::Model) => {
+                let model_tokenizer = model_tokenizer
+                    .ok_or_else(|| anyhow::anyhow!("--tokenizer model needs an embedding model with a local tokenizer"))?;
+                sized(capacity, model_tokenizer.clone(), overlap, always_markdown, settings)?
+            }
+            (ChunkerKind::Sentences, _) => TextChunker::Sentences { size: max_size, overlap, settings },
+            (ChunkerKind::Paragraphs, _) => TextChunker::Paragraphs { size: max_size, overlap, settings },
+            (ChunkerKind::Pages, _) => TextChunker::Pages { size: max_size, overlap, settings },
+        })
+    }
+}
+
+impl Chunker for TextChunker {
+    fn is_markdown(&self, document: &Document) -> bool {
+        match self {
+            TextChunker::Sized { always_markdown, .. } => *always_markdown || document.markdown,
+            _ => false,
+        }
+    }
+
+    fn settings(&self) -> serde_json::Value {
+        match self {
+            TextChunker::Sized { settings, .. }
+            | TextChunker::Sentences { settings, .. }
+            | TextChunker::Paragraphs { settings, .. }
+            | TextChunker::Pages { settings, .. } => settings.clone(),
+        }
+    }
+
+    fn chunks<'a>(&self, document: &'a Document) -> Vec<(usize, &'a str)> {
+        let text = document.text.as_str();
+        match self {
+            TextChunker::Sized { markdown, .. } if self.is_markdown(document) => markdown.split(text),
+            TextChunker::Sized { text: splitter, .. } => splitter.split(text),
+            TextChunker::Sentences { size, overlap, .. } => {
+                let sentences = text
+                    .split_sentence_bound_indices()
+                    .filter(|(_, sentence)| !sentence.trim().is_empty())
+                    .map(|(offset, sentence)| (offset, offset + sentence.len()))
+                    .collect::<Vec<_>>();
+                group(text, &sentences, *size, *overlap)
+            }
+            TextChunker::Paragraphs { size, overlap, .. } => group(text, &paragraphs(text), *size, *overlap),
+            TextChunker::Pages { size, overlap, .. } => group(text, &document.pages(), *size, *overlap),
+        }
+    }
+}
+
+/// Build the plain text and Markdown splitters for a sizer.
+fn sized<S>(capacity: ChunkCapacity, sizer: S, overlap: usize, always_markdown: bool, settings: serde_json::Value) -> anyhow::Result<TextChunker>
+where
+    S: ChunkSizer + Clone + Send + Sync + 'static,
+{
+    Ok(TextChunker::Sized {
+        text: Box::new(TextSplitter::new(config(capacity, sizer.clone(), overlap)?)),
+        markdown: Box::new(MarkdownSplitter::new(config(capacity, sizer, overlap)?)),
+        always_markdown,
+        settings,
+    })
+}
+
+/// Splitter settings for a sizer. `ChunkConfig` is not `Clone`, so each splitter is given its own.
+fn config<S: ChunkSizer>(capacity: ChunkCapacity, sizer: S, overlap: usize) -> anyhow::Result<ChunkConfig<S>> {
+    Ok(ChunkConfig::new(capacity).with_sizer(sizer).with_overlap(overlap)?.with_trim(true))
+}
+
+/// Byte ranges of the runs of non-blank lines in the text.
+fn paragraphs(text: &str) -> Vec<(usize, usize)> {
+    let mut paragraphs = Vec::new();
+    let mut start = None;
+    let mut position = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if let Some(start) = start.take() {
+                paragraphs.push((start, position));
+            }
+        } else if start.is_none() {
+            start = Some(position);
+        }
+        position += line.len();
+    }
+    if let Some(start) = start {
+        paragraphs.push((start, position));
+    }
+    paragraphs
+}
+
+/// Join consecutive units (sentences, paragraphs or pages) into chunks of `size` units,
+/// where each chunk repeats the last `overlap` units of the previous one.
+fn group<'a>(text: &'a str, units: &[(usize, usize)], size: usize, overlap: usize) -> Vec<(usize, &'a str)> {
+    let mut chunks = Vec::new();
+    let mut first = 0;
+    while first < units.len() {
+        let last = (first + size).min(units.len()) - 1;
+        let range = &text[units[first].0..units[last].1];
+        let trimmed = range.trim_start();
+        let offset = units[first].0 + range.len() - trimmed.len();
+        let trimmed = trimmed.trim_end();
+        if !trimmed.is_empty() {
+            chunks.push((offset, trimmed));
+        }
+        if last == units.len() - 1 {
+            break;
+        }
+        first += size - overlap;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Byte ranges of the words of the text.
+    fn words(text: &str) -> Vec<(usize, usize)> {
+        text.split_word_bound_indices()
+            .filter(|(_, word)| !word.trim().is_empty())
+            .map(|(offset, word)| (offset, offset + word.len()))
+            .collect()
+    }
+
+    #[test]
+    fn groups_overlap_by_the_last_units() {
+        let text = "a b c d e";
+        assert_eq!(group(text, &words(text), 3, 1), vec![(0, "a b c"), (4, "c d e")]);
+    }
+
+    #[test]
+    fn last_group_keeps_the_remaining_units() {
+        let text = "a b c d e";
+        assert_eq!(group(text, &words(text), 2, 0), vec![(0, "a b"), (4, "c d"), (8, "e")]);
+    }
+
+    #[test]
+    fn whitespace_only_groups_are_skipped() {
+        let text = "a   b";
+        let units = [(0, 1), (1, 4), (4, 5)];
+        assert_eq!(group(text, &units, 1, 0), vec![(0, "a"), (4, "b")]);
+    }
+
+    #[test]
+    fn no_units_make_no_chunks() {
+        assert!(group("", &[], 3, 1).is_empty());
+        assert!(group("  \n", &paragraphs("  \n"), 3, 1).is_empty());
+    }
+
+    #[test]
+    fn paragraphs_are_runs_of_non_blank_lines() {
+        let text = "one\ntwo\n\n  \nthree\n\nfour";
+        let paragraphs = paragraphs(text);
+        let texts = paragraphs.iter().map(|&(start, end)| &text[start..end]).collect::<Vec<_>>();
+        assert_eq!(texts, vec!["one\ntwo\n", "three\n", "four"]);
+    }
+
+    #[test]
+    fn paragraph_groups_start_at_their_first_character() {
+        let text = "\n\none\n\ntwo\n";
+        assert_eq!(group(text, &paragraphs(text), 1, 0), vec![(2, "one"), (7, "two")]);
+    }
+}
@@ -0,0 +1,423 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use qdrant_client::qdrant::Distance;
+
+use vectordb::cache::{self, ModelCache};
+use vectordb::chunker::{ChunkerKind, TokenizerKind};
+use vectordb::embedding::Backend;
+use vectordb::filter::{parse_expr, FilterExpr};
+use vectordb::onnx::Pooling;
+use vectordb::payload;
+use vectordb::sparse::SparseKind;
+
+/// Extract text from PDFs, embed it and manage the resulting Qdrant collections.
+#[derive(Debug, Parser)]
+#[command(name = "qdrant-pdf-uploader", version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Options shared by every subcommand.
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Print extracted text, chunks and embeddings while running
+    #[arg(short, long, global = true)]
+    pub debug: bool,
+
+    /// Never prompt; take the default answer (appending to existing collections when ingesting)
+    #[arg(short, long, global = true, visible_alias = "non-interactive")]
+    pub yes: bool,
+
+    /// Whether to start a Qdrant Docker container when no instance is reachable
+    #[arg(long, global = true, value_enum, default_value_t = StartQdrant::Ask)]
+    pub start_qdrant: StartQdrant,
+
+    /// Directory fastembed models are downloaded to and loaded from
+    #[arg(long, global = true, env = "FASTEMBED_CACHE_PATH", value_name = "DIR", default_value = cache::DEFAULT_DIR)]
+    pub model_cache_dir: PathBuf,
+
+    /// Never download models; fail when a model is not in the model cache
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+}
+
+impl GlobalArgs {
+    pub fn model_cache(&self) -> ModelCache {
+        ModelCache::new(&self.model_cache_dir, self.offline)
+    }
+}
+
+/// How to reach the Qdrant instance.
+#[derive(Debug, Args)]
+pub struct ConnectionArgs {
+    /// gRPC URL of the Qdrant instance
+    #[arg(long, global = true, env = "QDRANT_URL", default_value = "http://localhost:6334")]
+    pub url: String,
+
+    /// API key sent with every request
+    #[arg(long, global = true, env = "QDRANT_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// PEM file with the CA certificates to trust for https URLs instead of the system roots, such as the self-signed
+    /// certificate of a development cluster
+    #[arg(long, global = true, value_name = "PATH")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Seconds to wait for the connection to be established
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 5)]
+    pub connect_timeout: u64,
+
+    /// Seconds to wait for each request to complete
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 30)]
+    pub timeout: u64,
+}
+
+/// Where and how chunks and queries are embedded.
+#[derive(Debug, Args)]
+pub struct EmbedderArgs {
+    /// Where to compute the embeddings; defaults to fastembed, or for search to the backend the collection was ingested with
+    #[arg(long, value_enum)]
+    pub backend: Option<Backend>,
+
+    /// Directory with the model.onnx and tokenizer.json of a local model, for the onnx backend
+    #[arg(long, value_name = "DIR")]
+    pub model_path: Option<PathBuf>,
+
+    /// How the onnx backend combines token vectors; defaults to what a sentence-transformers export configures, or cls
+    #[arg(long, value_enum)]
+    pub pooling: Option<Pooling>,
+
+    /// Whether the onnx backend scales vectors to unit length; defaults to what a sentence-transformers export
+    /// configures, or true
+    #[arg(long, value_name = "BOOL")]
+    pub normalize: Option<bool>,
+
+    /// Base URL of the embedding API; defaults to https://api.openai.com/v1 with openai, http://localhost:11434 with
+    /// ollama and http://localhost:8080 with tei
+    #[arg(long, env = "EMBEDDING_URL", value_name = "URL")]
+    pub embedding_url: Option<String>,
+
+    /// API key of the embedding API, sent as a bearer token; the openai backend also reads OPENAI_API_KEY
+    #[arg(long, env = "EMBEDDING_API_KEY", hide_env_values = true)]
+    pub embedding_api_key: Option<String>,
+
+    /// Ask the embedding API for vectors of this size, for models that can shorten them
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub dimensions: Option<u64>,
+
+    /// Number of texts per request to the embedding API; tei never sends more than its server accepts
+    #[arg(long, default_value_t = 64, value_parser = positive)]
+    pub embedding_batch_size: usize,
+
+    /// Number of requests to the embedding API in flight at once; each batch of chunks has enough texts for all of them
+    #[arg(long, default_value_t = 1, value_parser = positive)]
+    pub embedding_concurrency: usize,
+
+    /// Number of times to try a request to the embedding API, waiting longer after each failure or rate limit
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub embedding_max_attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StartQdrant {
+    Never,
+    Always,
+    Ask,
+}
+
+/// What to do when ingesting into a collection that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnExisting {
+    /// Add to the collection, replacing changed files and skipping unchanged ones
+    Append,
+    /// Delete and recreate the collection
+    Recreate,
+    /// Stop with exit code 4
+    Fail,
+}
+
+/// How the vectors of a collection created by `import` are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VectorDistance {
+    Cosine,
+    Dot,
+    Euclid,
+    Manhattan,
+}
+
+impl From<VectorDistance> for Distance {
+    fn from(distance: VectorDistance) -> Self {
+        match distance {
+            VectorDistance::Cosine => Distance::Cosine,
+            VectorDistance::Dot => Distance::Dot,
+            VectorDistance::Euclid => Distance::Euclid,
+            VectorDistance::Manhattan => Distance::Manhattan,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Extract, chunk, embed and upload PDF and Markdown files
+    Ingest(IngestArgs),
+    /// Run a semantic search against a collection
+    #[command(visible_alias = "query")]
+    Search(SearchArgs),
+    /// List the collections in the Qdrant database
+    Collections,
+    /// Delete a collection
+    Delete(DeleteArgs),
+    /// Show point counts and status of a collection
+    Stats(StatsArgs),
+    /// List the supported embedding models and whether they are cached locally
+    ListModels,
+    /// Download, list and remove the models in the model cache
+    Models(ModelsArgs),
+    /// Upload the points that an ingest saved after running out of upload attempts
+    Replay(ReplayArgs),
+    /// Upload the points of a file written by `ingest --output`, without embedding them again
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct IngestArgs {
+    /// PDF or Markdown files, directories or glob patterns to ingest; `-` reads paths from stdin
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Glob that files found in directories must match
+    #[arg(short, long = "include", value_name = "GLOB", default_values = ["*.pdf", "*.md", "*.markdown"])]
+    pub include: Vec<String>,
+
+    /// Glob for files to skip
+    #[arg(short, long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// How to split documents into chunks
+    #[arg(long, value_enum, default_value_t = ChunkerKind::Tokens)]
+    pub chunker: ChunkerKind,
+
+    /// What the tokens and markdown chunkers measure chunk sizes in; `model` never exceeds what the embedding model reads
+    #[arg(long, value_enum, default_value_t = TokenizerKind::Cl100k)]
+    pub tokenizer: TokenizerKind,
+
+    /// Maximum chunk size, in tokens or characters, or the number of sentences, paragraphs or pages per chunk
+    #[arg(short = 's', long, default_value_t = 200, value_parser = positive)]
+    pub chunk_size: usize,
+
+    /// Minimum chunk size for the tokens and characters chunkers; chunks are grown towards `--chunk-size`
+    #[arg(long, value_parser = positive)]
+    pub min_chunk_size: Option<usize>,
+
+    /// How much of the end of each chunk is repeated at the start of the next, in the chunker's units
+    #[arg(long, default_value_t = 0)]
+    pub chunk_overlap: usize,
+
+    /// Name of the collection to upload to
+    #[arg(short, long, default_value = "test", value_parser = collection_name)]
+    pub collection: String,
+
+    /// What to do if the collection already exists; asks when not given, or appends with `--yes`
+    #[arg(long, value_enum)]
+    pub on_existing: Option<OnExisting>,
+
+    /// Extra `key=value` field stored in the payload of every chunk
+    #[arg(long = "metadata", value_name = "KEY=VALUE", value_parser = metadata_field)]
+    pub metadata: Vec<(String, serde_json::Value)>,
+
+    /// Embedding model; defaults to AllMiniLML6V2 with fastembed (see `list-models`), text-embedding-3-small with openai,
+    /// nomic-embed-text with ollama, the model the server runs with tei and the directory's name with onnx
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    #[command(flatten)]
+    pub embedder: EmbedderArgs,
+
+    /// Also store a sparse vector for each chunk, for `search --hybrid`
+    #[arg(long, value_enum)]
+    pub sparse: Option<SparseKind>,
+
+    /// Number of files to extract, chunk and embed at the same time
+    #[arg(short, long, default_value_t = 1, value_parser = positive)]
+    pub jobs: usize,
+
+    /// Continue an interrupted ingest into the collection, skipping the files and batches its journal records as uploaded
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Journal of uploaded files and batches; defaults to `.ingest_journal/<collection>.jsonl`
+    #[arg(long, value_name = "PATH")]
+    pub journal: Option<PathBuf>,
+
+    /// Number of times to try uploading a batch before saving its points for `replay`
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+
+    /// File the points of batches that could not be uploaded are appended to; defaults to `.ingest_failed/<collection>.jsonl`
+    #[arg(long, value_name = "PATH")]
+    pub failed_points: Option<PathBuf>,
+
+    /// Extract and chunk the files and print statistics, without connecting to Qdrant
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// With `--dry-run`, also embed the chunks to measure how long embedding takes
+    #[arg(long, requires = "dry_run")]
+    pub embed: bool,
+
+    /// Also write every point to this file, as JSON Lines (`.jsonl`) or Parquet (`.parquet`)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["dry_run", "resume"])]
+    pub output: Option<PathBuf>,
+
+    /// Only write the points to `--output`, without connecting to Qdrant
+    #[arg(long, requires = "output")]
+    pub output_only: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Text to search for
+    pub query: String,
+
+    /// Name of the collection to search
+    #[arg(short, long, default_value = "test", value_parser = collection_name)]
+    pub collection: String,
+
+    /// Number of hits to return
+    #[arg(short, long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub limit: u64,
+
+    /// Only return hits with at least this score
+    #[arg(short = 't', long, conflicts_with = "hybrid")]
+    pub score_threshold: Option<f32>,
+
+    /// Payload condition every hit must match, e.g. `file_name=report.pdf` or `chunk_number>=10`
+    #[arg(short, long = "filter", value_name = "EXPR", value_parser = parse_expr)]
+    pub filter: Vec<FilterExpr>,
+
+    /// Payload condition of which at least one must match
+    #[arg(long = "should", value_name = "EXPR", value_parser = parse_expr)]
+    pub should: Vec<FilterExpr>,
+
+    /// Payload condition no hit may match
+    #[arg(long = "must-not", value_name = "EXPR", value_parser = parse_expr)]
+    pub must_not: Vec<FilterExpr>,
+
+    /// Print the hits as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Embedding model to embed the query with, detected from the collection if not given
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    #[command(flatten)]
+    pub embedder: EmbedderArgs,
+
+    /// Also search the sparse vectors and fuse both result lists with reciprocal rank fusion
+    #[arg(long)]
+    pub hybrid: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// Name of the collection to delete
+    #[arg(value_parser = collection_name)]
+    pub collection: String,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Name of the collection to inspect
+    #[arg(default_value = "test", value_parser = collection_name)]
+    pub collection: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Name of the collection to upload to
+    #[arg(short, long, default_value = "test", value_parser = collection_name)]
+    pub collection: String,
+
+    /// File of saved points; defaults to `.ingest_failed/<collection>.jsonl`
+    #[arg(long, value_name = "PATH")]
+    pub file: Option<PathBuf>,
+
+    /// Number of times to try uploading a batch
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// File written by `ingest --output`, in JSON Lines or Parquet
+    pub file: PathBuf,
+
+    /// Name of the collection to upload to; it is created if it does not exist
+    #[arg(short, long, default_value = "test", value_parser = collection_name)]
+    pub collection: String,
+
+    /// Distance of the collection if it is created, which must be the one the points' model is meant for;
+    /// every built-in model uses cosine
+    #[arg(long, value_enum, default_value_t = VectorDistance::Cosine)]
+    pub distance: VectorDistance,
+
+    /// Number of times to try uploading a batch before saving its points for `replay`
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct ModelsArgs {
+    #[command(subcommand)]
+    pub command: ModelsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ModelsCommand {
+    /// Download models to the model cache, for machines that will run with --offline
+    Download {
+        /// Embedding models as listed by `list-models`
+        #[arg(required = true)]
+        models: Vec<String>,
+    },
+    /// List the models in the model cache with their size on disk
+    List,
+    /// Remove incomplete downloads and models this version no longer uses from the model cache
+    Prune {
+        /// Also remove every cached model except these
+        #[arg(long, value_name = "MODEL")]
+        keep: Vec<String>,
+    },
+}
+
+fn positive(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err(String::from("must be greater than zero")),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn metadata_field(value: &str) -> Result<(String, serde_json::Value), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), payload::parse_scalar(value.trim()))),
+        _ => Err(String::from("expected key=value")),
+    }
+}
+
+fn collection_name(value: &str) -> Result<String, String> {
+    if value.trim().is_empty() {
+        Err(String::from("collection name cannot be empty"))
+    } else {
+        Ok(value.to_string())
+    }
+}
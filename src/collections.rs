@@ -0,0 +1,50 @@
+use vectordb::qdrant;
+
+use crate::cli::{DeleteArgs, GlobalArgs, StatsArgs};
+use crate::{connection, prompt};
+
+pub async fn list(global: &GlobalArgs) -> anyhow::Result<()> {
+    let client = connection::connect(global).await?;
+    let collections_list = client.list_collections().await?;
+    if collections_list.collections.is_empty() {
+        println!("No collections found");
+    }
+    for collection in collections_list.collections {
+        println!("{}", collection.name);
+    }
+    Ok(())
+}
+
+pub async fn delete(args: DeleteArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let client = connection::connect(global).await?;
+    if !qdrant::collection_exists(&client, &args.collection).await? {
+        return Err(anyhow::anyhow!("Collection {} does not exist", args.collection));
+    }
+
+    // `--yes` confirms the deletion the user explicitly asked for
+    let question = format!("Do you want to delete collection {}?", args.collection);
+    if !global.yes && !prompt::confirm(global, &question, false)? {
+        println!("Collection was not deleted");
+        return Ok(());
+    }
+
+    client.delete_collection(&args.collection).await?;
+    println!("Collection {} deleted", args.collection);
+    Ok(())
+}
+
+pub async fn stats(args: StatsArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let client = connection::connect(global).await?;
+    let info = client
+        .collection_info(&args.collection)
+        .await?
+        .result
+        .ok_or_else(|| anyhow::anyhow!("No information returned for collection {}", args.collection))?;
+
+    println!("Collection: {}", args.collection);
+    println!("Status: {:?}", info.status());
+    println!("Points: {}", info.points_count.unwrap_or_default());
+    println!("Indexed vectors: {}", info.indexed_vectors_count.unwrap_or_default());
+    println!("Segments: {}", info.segments_count);
+    Ok(())
+}
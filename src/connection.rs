@@ -0,0 +1,113 @@
+use std::process::Command;
+use std::time::Duration;
+
+use qdrant_client::prelude::*;
+
+use crate::cli::{ConnectionArgs, GlobalArgs, StartQdrant};
+use crate::error::Failure;
+use crate::prompt;
+
+/// Number of one-second attempts to reach a freshly started Qdrant container
+const STARTUP_ATTEMPTS: u32 = 30;
+
+/// Check the `--ca-cert` file and make the TLS transport trust it.
+///
+/// Must be called before the runtime starts: the certificate file is passed through the environment, which is
+/// not safe to change while other threads may read it.
+pub fn trust_ca_cert(connection: &ConnectionArgs) -> anyhow::Result<()> {
+    let Some(ca_cert) = &connection.ca_cert else {
+        return Ok(());
+    };
+    let pem = std::fs::read_to_string(ca_cert)
+        .map_err(|e| anyhow::anyhow!("Failed to read the CA certificate {}: {}", ca_cert.display(), e))?;
+    if !pem.contains("-----BEGIN CERTIFICATE-----") {
+        return Err(anyhow::anyhow!("{} does not contain a PEM certificate", ca_cert.display()));
+    }
+    // The gRPC transport loads its TLS roots through rustls-native-certs, which honours SSL_CERT_FILE
+    std::env::set_var("SSL_CERT_FILE", ca_cert);
+    Ok(())
+}
+
+/// Connect to the configured Qdrant instance.
+///
+/// If it is not reachable and the URL points at this machine, a Qdrant Docker container is started according to `--start-qdrant`.
+pub async fn connect(global: &GlobalArgs) -> anyhow::Result<QdrantClient> {
+    let connection = &global.connection;
+    let error = match try_connect(connection).await {
+        Ok(client) => {
+            eprintln!("Connected to Qdrant database at {}", connection.url);
+            return Ok(client);
+        }
+        Err(e) => e,
+    };
+
+    eprintln!("Qdrant instance with Grpc not detected at {}: {:#}", connection.url, error);
+    if !is_local(&connection.url) {
+        return Err(Failure::QdrantUnavailable(connection.url.clone()).into());
+    }
+    let start = match global.start_qdrant {
+        StartQdrant::Never => false,
+        StartQdrant::Always => true,
+        StartQdrant::Ask => prompt::confirm(global, "Do you want to start a Qdrant instance?", true)?,
+    };
+    if !start {
+        return Err(Failure::QdrantUnavailable(connection.url.clone()).into());
+    }
+
+    eprintln!("Starting Qdrant instance...");
+    let output = Command::new("docker")
+        .args(["run", "-d", "-p", "6333:6333", "-p", "6334:6334", "-e", "QDRANT__SERVICE__GRPC_PORT=6334", "qdrant/qdrant"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to execute docker, make sure Docker is installed and running: {}", e))?;
+    if !output.status.success() {
+        eprintln!("docker run failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return Err(Failure::QdrantUnavailable(connection.url.clone()).into());
+    }
+
+    for _ in 0..STARTUP_ATTEMPTS {
+        // give the Qdrant instance time to start
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        if let Ok(client) = try_connect(connection).await {
+            eprintln!("Qdrant instance started! You can access the Qdrant dashboard at {}", dashboard_url(&connection.url));
+            eprintln!("Connected to Qdrant database at {}", connection.url);
+            return Ok(client);
+        }
+    }
+    Err(Failure::QdrantUnavailable(connection.url.clone()).into())
+}
+
+async fn try_connect(connection: &ConnectionArgs) -> anyhow::Result<QdrantClient> {
+    let client = QdrantClient::from_url(&connection.url)
+        .with_api_key(connection.api_key.clone())
+        .with_connect_timeout(Duration::from_secs(connection.connect_timeout))
+        .with_timeout(Duration::from_secs(connection.timeout))
+        .build()?;
+    client.list_collections().await?;
+    Ok(client)
+}
+
+/// Split a URL into its scheme, host and port.
+fn split_url(url: &str) -> (&str, &str, Option<&str>) {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let authority = rest.split('/').next().unwrap_or_default();
+    match authority.rsplit_once(':') {
+        // Don't mistake the colons of a bracketed IPv6 address for a port
+        Some((host, port)) if !port.contains(']') => (scheme, host, Some(port)),
+        _ => (scheme, authority, None),
+    }
+}
+
+/// Whether the URL points at this machine, where a Docker container can be started.
+fn is_local(url: &str) -> bool {
+    let (_, host, _) = split_url(url);
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// URL of the web dashboard served on the REST port next to the gRPC port.
+pub fn dashboard_url(url: &str) -> String {
+    match split_url(url) {
+        (scheme, host, Some("6334")) => format!("{}://{}:6333/dashboard/", scheme, host),
+        (scheme, host, Some(port)) => format!("{}://{}:{}/dashboard/", scheme, host, port),
+        (scheme, host, None) => format!("{}://{}/dashboard/", scheme, host),
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use pdf_extract::extract_text_from_mem_by_pages;
+
+use crate::pipeline::Extractor;
+
+/// Text extracted from a document, with the byte offsets at which each page starts.
+pub struct Document {
+    pub text: String,
+    /// Whether the text is Markdown, so it should be split along its structure
+    pub markdown: bool,
+    page_starts: Vec<usize>,
+}
+
+impl Document {
+    /// Read the document's text according to the file extension: Markdown for `.md` and `.markdown`, PDF otherwise.
+    pub fn load(path: &Path, bytes: &[u8]) -> anyhow::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown") => {
+                Ok(Document::from_markdown(bytes))
+            }
+            _ => Document::from_pdf(bytes),
+        }
+    }
+
+    /// A Markdown file is treated as a single page.
+    pub fn from_markdown(bytes: &[u8]) -> Self {
+        Document {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            markdown: true,
+            page_starts: vec![0],
+        }
+    }
+
+    /// Extract the text of a PDF page by page, joining the pages with a newline.
+    pub fn from_pdf(bytes: &[u8]) -> anyhow::Result<Self> {
+        let pages = extract_text_from_mem_by_pages(bytes)?;
+        let mut text = String::new();
+        let mut page_starts = Vec::with_capacity(pages.len());
+        for page in pages {
+            page_starts.push(text.len());
+            text.push_str(&page);
+            text.push('\n');
+        }
+        Ok(Document { text, markdown: false, page_starts })
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.page_starts.len()
+    }
+
+    /// Byte ranges of the pages in the text.
+    pub fn pages(&self) -> Vec<(usize, usize)> {
+        let ends = self.page_starts.iter().skip(1).copied().chain([self.text.len()]);
+        self.page_starts.iter().copied().zip(ends).collect()
+    }
+
+    /// 1-based number of the page containing the given byte offset.
+    pub fn page_at(&self, offset: usize) -> usize {
+        self.page_starts.partition_point(|&start| start <= offset).max(1)
+    }
+}
+
+/// Reads PDFs page by page with `pdf_extract`, and Markdown files as they are.
+pub struct DocumentExtractor;
+
+impl Extractor for DocumentExtractor {
+    fn name(&self) -> String {
+        String::from("pdf-pages,markdown")
+    }
+
+    fn extract(&self, path: &Path, bytes: &[u8]) -> anyhow::Result<Document> {
+        Document::load(path, bytes)
+    }
+}
+
+/// Converts increasing byte offsets into character offsets without rescanning the text each time.
+pub struct CharCounter<'a> {
+    text: &'a str,
+    byte: usize,
+    chars: usize,
+}
+
+impl<'a> CharCounter<'a> {
+    pub fn new(text: &'a str) -> Self {
+        CharCounter { text, byte: 0, chars: 0 }
+    }
+
+    pub fn char_offset(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            self.byte = 0;
+            self.chars = 0;
+        }
+        self.chars += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A document with the given page texts, laid out like an extracted PDF.
+    fn pdf(pages: &[&str]) -> Document {
+        let mut text = String::new();
+        let mut page_starts = Vec::new();
+        for page in pages {
+            page_starts.push(text.len());
+            text.push_str(page);
+            text.push('\n');
+        }
+        Document { text, markdown: false, page_starts }
+    }
+
+    #[test]
+    fn pages_start_at_their_first_byte() {
+        let document = pdf(&["ab", "cd"]);
+        assert_eq!(document.pages(), vec![(0, 3), (3, 6)]);
+        assert_eq!(document.page_at(0), 1);
+        // The newline joining the pages belongs to the first one
+        assert_eq!(document.page_at(2), 1);
+        assert_eq!(document.page_at(3), 2);
+        assert_eq!(document.page_at(5), 2);
+    }
+
+    #[test]
+    fn chunk_spanning_pages() {
+        let document = pdf(&["first page", "second page", "third page"]);
+        let start = document.text.find("page").unwrap();
+        let end = document.text.find("third").unwrap() + "third".len();
+        assert_eq!(document.page_at(start), 1);
+        assert_eq!(document.page_at(end - 1), 3);
+    }
+
+    #[test]
+    fn empty_pages_keep_their_number() {
+        let document = pdf(&["a", "", "b"]);
+        assert_eq!(document.page_count(), 3);
+        assert_eq!(document.pages(), vec![(0, 2), (2, 3), (3, 5)]);
+        assert_eq!(document.page_at(2), 2);
+        assert_eq!(document.page_at(3), 3);
+    }
+
+    #[test]
+    fn document_without_pages_is_on_page_one() {
+        let document = pdf(&[]);
+        assert_eq!(document.page_count(), 0);
+        assert_eq!(document.page_at(0), 1);
+    }
+
+    #[test]
+    fn markdown_is_a_single_page() {
+        let document = Document::from_markdown("# Title\n\nBody\n".as_bytes());
+        assert_eq!(document.pages(), vec![(0, 14)]);
+        assert_eq!(document.page_at(13), 1);
+    }
+
+    #[test]
+    fn char_offsets_of_multi_byte_text() {
+        let text = "héllo wörld ✓";
+        let mut counter = CharCounter::new(text);
+        assert_eq!(counter.char_offset(0), 0);
+        assert_eq!(counter.char_offset(text.find("wörld").unwrap()), 6);
+        assert_eq!(counter.char_offset(text.find('✓').unwrap()), 12);
+        assert_eq!(counter.char_offset(text.len()), 13);
+    }
+
+    #[test]
+    fn char_offsets_restart_when_going_back() {
+        let text = "ünïcödé text";
+        let mut counter = CharCounter::new(text);
+        assert_eq!(counter.char_offset(text.len()), 12);
+        assert_eq!(counter.char_offset(text.find("text").unwrap()), 8);
+    }
+}
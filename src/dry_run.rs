@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use tokenizers::Tokenizer;
+use vectordb::chunker::{TokenCounter, TokenizerKind};
+use vectordb::embedding;
+use vectordb::processor::{Payloads, Processor};
+use vectordb::InputFile;
+
+/// Statistics about what an ingest would upload.
+#[derive(Default)]
+struct Stats {
+    files: usize,
+    pages: usize,
+    chunks: usize,
+    min_tokens: Option<usize>,
+    max_tokens: usize,
+    total_tokens: usize,
+    truncated: usize,
+    payload_bytes: usize,
+    embedding_time: Duration,
+}
+
+/// What chunks are measured with: the model's own tokenizer when it runs locally, and otherwise the tokenizer
+/// chunks are sized with.
+enum Counter<'a> {
+    Model(&'a Tokenizer),
+    Chunking(TokenCounter),
+}
+
+impl Counter<'_> {
+    fn count(&self, text: &str) -> anyhow::Result<usize> {
+        match self {
+            Counter::Model(tokenizer) => embedding::count_tokens(tokenizer, text),
+            Counter::Chunking(counter) => Ok(counter.count(text)),
+        }
+    }
+}
+
+/// Extract and chunk the files, and embed the chunks if asked to, then print what would be uploaded.
+///
+/// Nothing is sent to Qdrant, so no connection is made. `dimensions` is `None` when an embedding API was not
+/// asked for the size of its vectors.
+pub async fn run(
+    processor: &Processor,
+    files: &[InputFile],
+    dimensions: Option<u64>,
+    tokenizer: TokenizerKind,
+    embed: bool,
+) -> anyhow::Result<()> {
+    println!("Dry run: nothing will be uploaded");
+    let (counter, counted_with) = match processor.embedder.tokenizer() {
+        Some(model_tokenizer) => (Counter::Model(model_tokenizer), format!("{} tokenizer", processor.embedder.model_name())),
+        None => (
+            Counter::Chunking(TokenCounter::new(tokenizer)?),
+            format!("{} tokenizer used for chunking, the model's own is not available locally", tokenizer.name()),
+        ),
+    };
+    let mut stats = Stats::default();
+    let mut failed = 0;
+    for file in files {
+        if let Err(e) = measure_file(processor, file, &counter, embed, &mut stats).await {
+            failed += 1;
+            println!("Failed to process {}: {}", file.path.display(), e);
+        }
+    }
+
+    println!();
+    println!("Dry run summary:");
+    println!("  Files:            {} ({} pages)", stats.files, stats.pages);
+    println!("  Chunks:           {}", stats.chunks);
+    if stats.chunks > 0 {
+        println!(
+            "  Tokens per chunk: min {}, avg {:.1}, max {} ({})",
+            stats.min_tokens.unwrap_or_default(),
+            stats.total_tokens as f64 / stats.chunks as f64,
+            stats.max_tokens,
+            counted_with
+        );
+    }
+    if let Some(max_tokens) = processor.embedder.max_tokens() {
+        println!("  Truncated chunks: {} (longer than the {} tokens the model reads)", stats.truncated, max_tokens);
+    }
+    match dimensions {
+        Some(dimensions) => println!(
+            "  Dense vectors:    {} of {} dimensions ({})",
+            stats.chunks,
+            dimensions,
+            format_bytes(stats.chunks * dimensions as usize * std::mem::size_of::<f32>())
+        ),
+        None => println!("  Dense vectors:    {} (the API's vector size is only asked with --embed)", stats.chunks),
+    }
+    if let Some((sparse, _)) = &processor.sparse {
+        println!("  Sparse vectors:   {} ({})", stats.chunks, sparse.name());
+    }
+    println!("  Payloads:         {}", format_bytes(stats.payload_bytes));
+    if embed && stats.chunks > 0 {
+        let seconds = stats.embedding_time.as_secs_f64();
+        println!(
+            "  Embedding time:   {:.1}s ({:.1} chunks/s)",
+            seconds,
+            stats.chunks as f64 / seconds.max(f64::EPSILON)
+        );
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} file(s) failed to process", failed, files.len()));
+    }
+    Ok(())
+}
+
+async fn measure_file(
+    processor: &Processor,
+    file: &InputFile,
+    counter: &Counter<'_>,
+    embed: bool,
+    stats: &mut Stats,
+) -> anyhow::Result<()> {
+    let path = file.path.as_path();
+    let bytes = std::fs::read(path)?;
+    let (file_hash, fingerprint) = processor.fingerprint(&bytes, &file.source);
+    let document = processor.extract(path, &bytes)?;
+    let chunks = processor.chunks(path, &document);
+
+    let markdown = processor.chunker.is_markdown(&document);
+    let mut payloads = Payloads::new(file, &file_hash, &fingerprint, &document, &chunks, markdown);
+    for (chunk_number, &(offset, text)) in chunks.iter().enumerate() {
+        let tokens = counter.count(text)?;
+        stats.min_tokens = Some(stats.min_tokens.map_or(tokens, |min_tokens| min_tokens.min(tokens)));
+        stats.max_tokens = stats.max_tokens.max(tokens);
+        stats.total_tokens += tokens;
+        if processor.embedder.max_tokens().is_some_and(|max_tokens| tokens > max_tokens) {
+            stats.truncated += 1;
+        }
+        stats.payload_bytes += processor.payload(&mut payloads, chunk_number, offset, text).to_string().len();
+    }
+
+    if embed {
+        let start = Instant::now();
+        let mut payloads = Payloads::new(file, &file_hash, &fingerprint, &document, &chunks, markdown);
+        let mut truncated = 0;
+        let batch_size = processor.embedder.batch_size();
+        for (batch_number, batch) in chunks.chunks(batch_size).enumerate() {
+            processor
+                .embed_batch(batch_number * batch_size, batch, &mut payloads, &mut truncated)
+                .await?;
+        }
+        stats.embedding_time += start.elapsed();
+    }
+
+    stats.files += 1;
+    stats.pages += document.page_count();
+    stats.chunks += chunks.len();
+    Ok(())
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
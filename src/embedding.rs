@@ -0,0 +1,196 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use fastembed::{EmbeddingModel, InitOptions, ModelInfo, TextEmbedding};
+use qdrant_client::qdrant::Distance;
+use tokenizers::Tokenizer;
+
+use crate::cache::ModelCache;
+use crate::pipeline::Embedder;
+use crate::runtime;
+
+/// Where chunks and queries are embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// A fastembed model running in-process
+    Fastembed,
+    /// An OpenAI-compatible `/embeddings` endpoint, like OpenAI, Azure OpenAI, llama.cpp or vLLM
+    Openai,
+    /// The `/api/embed` endpoint of an Ollama server
+    Ollama,
+    /// The `/embed` endpoint of a Hugging Face Text Embeddings Inference server
+    Tei,
+    /// An ONNX model and tokenizer in a local directory, given with --model-path
+    Onnx,
+}
+
+impl Backend {
+    /// Name as accepted by `--backend` and recorded in payloads.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Fastembed => "fastembed",
+            Backend::Openai => "openai",
+            Backend::Ollama => "ollama",
+            Backend::Tei => "tei",
+            Backend::Onnx => "onnx",
+        }
+    }
+
+    /// Whether chunks are sent to an embedding server rather than embedded in-process.
+    pub fn is_api(&self) -> bool {
+        matches!(self, Backend::Openai | Backend::Ollama | Backend::Tei)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::value_variants().iter().copied().find(|backend| backend.name() == name)
+    }
+}
+
+/// Name of a model as accepted by `--model`.
+pub fn model_name(model: &EmbeddingModel) -> String {
+    format!("{:?}", model)
+}
+
+/// Parse a model name as printed by `list-models`, ignoring case.
+pub fn parse_model(name: &str) -> Result<EmbeddingModel, String> {
+    TextEmbedding::list_supported_models()
+        .into_iter()
+        .map(|info| info.model)
+        .find(|model| model_name(model).eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let names = TextEmbedding::list_supported_models()
+                .iter()
+                .map(|info| model_name(&info.model))
+                .collect::<Vec<_>>();
+            format!("unknown model, expected one of: {}", names.join(", "))
+        })
+}
+
+pub fn model_info(model: &EmbeddingModel) -> ModelInfo<EmbeddingModel> {
+    TextEmbedding::get_model_info(model)
+}
+
+/// Size of the vectors produced by the model.
+pub fn vector_size(model: &EmbeddingModel) -> u64 {
+    model_info(model).dim as u64
+}
+
+/// Text embedded when connecting to a backend that doesn't say how long its vectors are.
+pub(crate) const PROBE: &[&str] = &["dimensions"];
+
+/// Size of the vector the backend at `source` returned for [`PROBE`].
+pub(crate) fn probed_dimensions(source: impl std::fmt::Display, vectors: Vec<Vec<f32>>) -> anyhow::Result<u64> {
+    match vectors.first() {
+        Some(vector) => Ok(vector.len() as u64),
+        None => Err(anyhow::anyhow!("{} returned no vector", source)),
+    }
+}
+
+/// Distance the model's embeddings are meant to be compared with.
+///
+/// Every dense model in fastembed's catalogue is trained for cosine similarity.
+pub fn distance(_model: &EmbeddingModel) -> Distance {
+    Distance::Cosine
+}
+
+/// Number of tokens the model reads before truncating its input.
+///
+/// This is the sequence length the model was trained with, capped at the 512 tokens fastembed truncates to.
+pub fn max_tokens(model: &EmbeddingModel) -> usize {
+    match model {
+        EmbeddingModel::AllMiniLML6V2
+        | EmbeddingModel::AllMiniLML6V2Q
+        | EmbeddingModel::AllMiniLML12V2
+        | EmbeddingModel::AllMiniLML12V2Q => 256,
+        EmbeddingModel::ParaphraseMLMiniLML12V2
+        | EmbeddingModel::ParaphraseMLMiniLML12V2Q
+        | EmbeddingModel::ParaphraseMLMpnetBaseV2 => 128,
+        _ => 512,
+    }
+}
+
+/// The model's tokenizer without fastembed's truncation and padding, for measuring how long a text is.
+pub fn measuring_tokenizer(model: &TextEmbedding) -> anyhow::Result<Tokenizer> {
+    let mut tokenizer = model.tokenizer.clone();
+    tokenizer.with_truncation(None).map_err(anyhow::Error::msg)?;
+    tokenizer.with_padding(None);
+    Ok(tokenizer.into())
+}
+
+/// Number of special tokens (like `[CLS]` and `[SEP]`) the tokenizer adds around every input.
+pub fn special_token_count(tokenizer: &Tokenizer) -> anyhow::Result<usize> {
+    Ok(tokenizer.encode("", true).map_err(anyhow::Error::msg)?.len())
+}
+
+/// Number of tokens the model reads for the text, including its special tokens.
+pub fn count_tokens(tokenizer: &Tokenizer, text: &str) -> anyhow::Result<usize> {
+    Ok(tokenizer.encode(text, true).map_err(anyhow::Error::msg)?.len())
+}
+
+/// Count the chunks that are longer than the model reads, including the special tokens it adds.
+pub fn count_truncated(tokenizer: &Tokenizer, max_tokens: usize, chunks: &[&str]) -> anyhow::Result<usize> {
+    let mut truncated = 0;
+    for chunk in chunks {
+        if count_tokens(tokenizer, chunk)? > max_tokens {
+            truncated += 1;
+        }
+    }
+    Ok(truncated)
+}
+
+/// Create the embedding model used for ingestion and search, downloading it to the cache first if needed and allowed.
+pub fn load_model(model: EmbeddingModel, cache: &ModelCache) -> anyhow::Result<TextEmbedding> {
+    cache.check(&model_name(&model), cache.is_cached(&model))?;
+    TextEmbedding::try_new(InitOptions {
+        model_name: model,
+        cache_dir: cache.dir.clone(),
+        show_download_progress: true,
+        ..Default::default()
+    })
+}
+
+/// A fastembed model running in-process.
+pub struct FastEmbedder {
+    model: EmbeddingModel,
+    embedding: TextEmbedding,
+    measuring_tokenizer: Tokenizer,
+}
+
+impl FastEmbedder {
+    /// Load the model, downloading it to the cache first if needed and allowed.
+    pub fn new(model: EmbeddingModel, cache: &ModelCache) -> anyhow::Result<Self> {
+        let embedding = load_model(model.clone(), cache)?;
+        let measuring_tokenizer = measuring_tokenizer(&embedding)?;
+        Ok(FastEmbedder { model, embedding, measuring_tokenizer })
+    }
+}
+
+#[async_trait]
+impl Embedder for FastEmbedder {
+    fn backend(&self) -> String {
+        Backend::Fastembed.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        model_name(&self.model)
+    }
+
+    fn dimensions(&self) -> u64 {
+        vector_size(&self.model)
+    }
+
+    fn distance(&self) -> Distance {
+        distance(&self.model)
+    }
+
+    fn tokenizer(&self) -> Option<&Tokenizer> {
+        Some(&self.measuring_tokenizer)
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        Some(max_tokens(&self.model))
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        runtime::blocking(|| self.embedding.embed(texts.to_vec(), None))
+    }
+}
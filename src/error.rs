@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Failures that map to a dedicated process exit code, so scripts can tell them apart.
+///
+/// Any other error exits with code 1, and invalid arguments exit with code 2.
+#[derive(Debug)]
+pub enum Failure {
+    /// Qdrant could not be reached and was not (or could not be) started
+    QdrantUnavailable(String),
+    /// The target collection exists and `--on-existing=fail` was given
+    CollectionExists(String),
+    /// A question needed an answer, but there is no terminal to ask on
+    NeedsConfirmation(String),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Failure::QdrantUnavailable(_) => 3,
+            Failure::CollectionExists(_) => 4,
+            Failure::NeedsConfirmation(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::QdrantUnavailable(url) => write!(f, "Cannot proceed without Qdrant instance at {}", url),
+            Failure::CollectionExists(name) => write!(f, "Collection {} already exists", name),
+            Failure::NeedsConfirmation(question) => write!(
+                f,
+                "Cannot ask \"{}\" without a terminal; pass --yes or the matching policy option",
+                question
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
@@ -0,0 +1,155 @@
+use qdrant_client::qdrant::{Condition, Filter, Range};
+use serde_json::Value;
+
+use crate::payload;
+
+const OPERATORS: [&str; 6] = ["!=", ">=", "<=", "=", ">", "<"];
+
+/// A single `key<op>value` payload condition given on the command line,
+/// e.g. `file_name=report.pdf` or `chunk_number>=10`.
+#[derive(Debug, Clone)]
+pub struct FilterExpr {
+    key: String,
+    op: &'static str,
+    value: Value,
+}
+
+/// Parse a filter expression, for use as a clap value parser.
+pub fn parse_expr(expr: &str) -> Result<FilterExpr, String> {
+    let (position, op) = OPERATORS
+        .iter()
+        .filter_map(|op| expr.find(op).map(|position| (position, *op)))
+        // The leftmost operator wins, and at the same position the two-character form wins
+        .min_by_key(|(position, op)| (*position, usize::MAX - op.len()))
+        .ok_or_else(|| format!("expected key=value, key!=value, key>value, key>=value, key<value or key<=value, got {}", expr))?;
+
+    let key = expr[..position].trim();
+    if key.is_empty() {
+        return Err(format!("missing payload key in {}", expr));
+    }
+    let value = payload::parse_scalar(expr[position + op.len()..].trim());
+    if op != "=" && op != "!=" && !value.is_number() {
+        return Err(format!("{} requires a numeric value in {}", op, expr));
+    }
+
+    Ok(FilterExpr { key: key.to_string(), op, value })
+}
+
+impl FilterExpr {
+    /// Convert to a Qdrant condition, returning whether it has to be negated (`!=`).
+    fn to_condition(&self) -> (Condition, bool) {
+        let key = self.key.clone();
+        let number = self.value.as_f64();
+        let condition = match self.op {
+            "=" | "!=" => match &self.value {
+                Value::Bool(b) => Condition::matches(key, *b),
+                Value::Number(n) if n.is_i64() => Condition::matches(key, n.as_i64().unwrap()),
+                Value::Number(_) => Condition::range(key, Range { gte: number, lte: number, ..Default::default() }),
+                Value::String(s) => Condition::matches(key, s.clone()),
+                other => Condition::matches(key, other.to_string()),
+            },
+            ">" => Condition::range(key, Range { gt: number, ..Default::default() }),
+            ">=" => Condition::range(key, Range { gte: number, ..Default::default() }),
+            "<" => Condition::range(key, Range { lt: number, ..Default::default() }),
+            "<=" => Condition::range(key, Range { lte: number, ..Default::default() }),
+            _ => unreachable!("operator {} is not handled", self.op),
+        };
+        (condition, self.op == "!=")
+    }
+}
+
+/// Build a Qdrant filter from the conditions that must, should and must not match.
+///
+/// Returns `None` if no conditions were given.
+pub fn build(must: &[FilterExpr], should: &[FilterExpr], must_not: &[FilterExpr]) -> Option<Filter> {
+    if must.is_empty() && should.is_empty() && must_not.is_empty() {
+        return None;
+    }
+
+    let mut filter = Filter::default();
+    for expr in must {
+        match expr.to_condition() {
+            (condition, false) => filter.must.push(condition),
+            (condition, true) => filter.must_not.push(condition),
+        }
+    }
+    for expr in must_not {
+        match expr.to_condition() {
+            (condition, false) => filter.must_not.push(condition),
+            (condition, true) => filter.must.push(condition),
+        }
+    }
+    for expr in should {
+        match expr.to_condition() {
+            (condition, false) => filter.should.push(condition),
+            (condition, true) => filter.should.push(Filter::must_not([condition]).into()),
+        }
+    }
+    Some(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(expr: &str) -> FilterExpr {
+        parse_expr(expr).unwrap()
+    }
+
+    #[test]
+    fn leftmost_operator_wins() {
+        let expr = parse("a=b>=c");
+        assert_eq!(expr.key, "a");
+        assert_eq!(expr.op, "=");
+        assert_eq!(expr.value, Value::from("b>=c"));
+    }
+
+    #[test]
+    fn two_character_operator_wins_at_the_same_position() {
+        let expr = parse("chunk_number>=10");
+        assert_eq!(expr.key, "chunk_number");
+        assert_eq!(expr.op, ">=");
+        assert_eq!(expr.value, Value::from(10));
+        assert_eq!(parse("page_start<=3").op, "<=");
+        assert_eq!(parse("department!=hr").op, "!=");
+    }
+
+    #[test]
+    fn comparisons_require_numbers() {
+        assert!(parse_expr("file_name>report").is_err());
+        assert!(parse_expr("=report").is_err());
+        assert!(parse_expr("report").is_err());
+    }
+
+    #[test]
+    fn negation_in_should_is_a_nested_filter() {
+        let filter = build(&[], &[parse("department!=hr")], &[]).unwrap();
+        assert!(filter.must.is_empty());
+        assert!(filter.must_not.is_empty());
+        let negated = Filter::must_not([Condition::matches("department", "hr".to_string())]);
+        assert_eq!(filter.should, vec![Condition::from(negated)]);
+    }
+
+    #[test]
+    fn negation_swaps_must_and_must_not() {
+        let filter = build(&[parse("department!=hr")], &[], &[parse("year!=2024")]).unwrap();
+        assert_eq!(filter.must_not, vec![Condition::matches("department", "hr".to_string())]);
+        assert_eq!(filter.must, vec![Condition::matches("year", 2024_i64)]);
+    }
+
+    #[test]
+    fn floats_are_matched_as_ranges() {
+        let filter = build(&[parse("score=0.5")], &[], &[]).unwrap();
+        let range = Range {
+            gte: Some(0.5),
+            lte: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(filter.must, vec![Condition::range("score", range)]);
+    }
+
+    #[test]
+    fn no_conditions_build_no_filter() {
+        assert!(build(&[], &[], &[]).is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::vectors::VectorsOptions;
+use qdrant_client::qdrant::VectorParams;
+
+use vectordb::pipeline::BATCH_SIZE;
+use vectordb::qdrant;
+use vectordb::sink::{append_jsonl, PointReader};
+use vectordb::sparse::{DENSE_VECTOR, SPARSE_VECTOR};
+
+use crate::cli::{GlobalArgs, ImportArgs};
+use crate::{connection, replay};
+
+/// Upload the points of a file written by `ingest --output`, creating the collection for them if needed.
+///
+/// Batches that still fail after all attempts are saved for `replay`, like during an ingest.
+pub async fn run(args: ImportArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let mut reader = PointReader::open(&args.file, BATCH_SIZE)?;
+    let Some(first) = reader.next_batch()? else {
+        println!("No points to import in {}", args.file.display());
+        return Ok(());
+    };
+    let (size, sparse) = vector_layout(&first[0])?;
+    println!("Importing points from {} into collection {}", args.file.display(), args.collection);
+
+    let client = connection::connect(global).await?;
+    if qdrant::collection_exists(&client, &args.collection).await? {
+        qdrant::check_vectors(&client, &args.collection, size, sparse).await?;
+    } else {
+        let params = VectorParams {
+            size,
+            distance: Distance::from(args.distance).into(),
+            ..Default::default()
+        };
+        qdrant::create_collection(&client, &args.collection, params, sparse).await?;
+        println!("Created collection {}", args.collection);
+    }
+    qdrant::create_payload_indexes(&client, &args.collection).await?;
+
+    let failed_points = replay::default_path(&args.collection);
+    let mut uploaded = 0;
+    let mut unsent = 0;
+    let mut batch = Some(first);
+    while let Some(points) = batch {
+        match qdrant::upsert_with_retry(&client, &args.collection, &points, args.max_attempts).await {
+            Ok(()) => uploaded += points.len(),
+            Err(e) => {
+                println!("{:#}", e);
+                append_jsonl(&failed_points, &points)?;
+                unsent += points.len();
+            }
+        }
+        batch = reader.next_batch()?;
+    }
+    println!("Imported {} point(s) into collection {}", uploaded, args.collection);
+
+    if unsent > 0 {
+        return Err(anyhow::anyhow!(
+            "{} point(s) could not be uploaded and were saved to {}, upload them with `replay`",
+            unsent,
+            failed_points.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Size of the point's dense vector, and whether it also has a sparse vector.
+fn vector_layout(point: &PointStruct) -> anyhow::Result<(u64, bool)> {
+    match point.vectors.as_ref().and_then(|vectors| vectors.vectors_options.as_ref()) {
+        Some(VectorsOptions::Vector(vector)) => Ok((vector.data.len() as u64, false)),
+        Some(VectorsOptions::Vectors(named)) => match named.vectors.get(DENSE_VECTOR) {
+            Some(dense) => Ok((dense.data.len() as u64, named.vectors.contains_key(SPARSE_VECTOR))),
+            None => Err(anyhow::anyhow!("Points have no vector named {}", DENSE_VECTOR)),
+        },
+        None => Err(anyhow::anyhow!("Points have no vectors")),
+    }
+}
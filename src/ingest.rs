@@ -0,0 +1,237 @@
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::VectorParams;
+use vectordb::embedding::Backend;
+use vectordb::journal::Journal;
+use vectordb::sparse::{SparseEncoder, SparseKind};
+use vectordb::{embedding, qdrant, ChunkSettings, Embedder, FileOutcome, FileSink, IngestPipeline, QdrantSink, TextChunker};
+
+use crate::cli::{GlobalArgs, IngestArgs, OnExisting};
+use crate::error::Failure;
+use crate::{backend, connection, dry_run, inputs, prompt, replay};
+
+pub async fn run(args: IngestArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let IngestArgs {
+        paths,
+        include,
+        exclude,
+        chunker,
+        tokenizer,
+        chunk_size,
+        min_chunk_size,
+        chunk_overlap,
+        collection: collection_name,
+        on_existing,
+        metadata,
+        model,
+        embedder: embedder_args,
+        sparse,
+        jobs,
+        resume,
+        journal,
+        max_attempts,
+        failed_points,
+        dry_run,
+        embed,
+        output,
+        output_only,
+    } = args;
+
+    let mut chunk_settings = ChunkSettings {
+        kind: chunker,
+        tokenizer,
+        min_size: min_chunk_size,
+        max_size: chunk_size,
+        overlap: chunk_overlap,
+    };
+    chunk_settings.validate()?;
+    if resume && on_existing == Some(OnExisting::Recreate) {
+        return Err(anyhow::anyhow!("--resume continues an ingest into the collection, it cannot be combined with --on-existing recreate"));
+    }
+
+    let files = inputs::collect(&paths, &include, &exclude)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No files to ingest"));
+    }
+    println!("Found {} file(s) to ingest", files.len());
+
+    let cache = global.model_cache();
+    let backend = embedder_args.backend.unwrap_or(Backend::Fastembed);
+    let connected = !(dry_run && !embed && backend.is_api());
+    let embedder = if connected {
+        backend::load(backend, &embedder_args, model, &cache).await?
+    } else {
+        backend::unconnected(backend, &embedder_args, model)?
+    };
+    if let (Some(tokenizer), Some(max_tokens)) = (embedder.tokenizer(), embedder.max_tokens()) {
+        // The model's special tokens take part of its input length
+        let token_budget = max_tokens.saturating_sub(embedding::special_token_count(tokenizer)?);
+        if chunk_settings.clamp_to_model(token_budget) {
+            println!("Chunk size reduced to {} tokens, the most {} reads", token_budget, embedder.model_name());
+        }
+    }
+    let chunker = TextChunker::new(&chunk_settings, embedder.tokenizer())?;
+    println!("Chunking: {}", chunk_settings.to_json());
+
+    let mut builder = IngestPipeline::builder()
+        .chunker(chunker)
+        .metadata(metadata)
+        .jobs(jobs);
+    if let Some(sparse) = sparse {
+        builder = builder.sparse(sparse, SparseEncoder::load(sparse)?);
+    }
+    if dry_run {
+        let dimensions = connected.then(|| embedder.dimensions());
+        let processor = builder.embedder(embedder).build_processor()?;
+        return dry_run::run(&processor, &files, dimensions, chunk_settings.tokenizer, embed).await;
+    }
+
+    println!("Collection name: {}", collection_name);
+    println!("Embedding model: {} ({})", embedder.model_name(), embedder.backend());
+    if let Some(sparse) = sparse {
+        println!("Sparse vectors: {}", sparse.name());
+    }
+
+    if let Some(output) = &output {
+        println!("Writing points to {}", output.display());
+        builder = builder.sink(FileSink::create(output)?);
+    }
+    if output_only {
+        println!("Points will not be uploaded to Qdrant");
+    } else {
+        let client = connection::connect(global).await?;
+        let should_create = prepare_collection(&client, global, &collection_name, on_existing, resume, embedder.as_ref(), sparse).await?;
+
+        // A new collection starts with an empty journal
+        if resume && should_create {
+            println!("Collection {} is new, so there is nothing to resume", collection_name);
+        }
+        let journal_path = journal.unwrap_or_else(|| Journal::default_path(&collection_name));
+        builder = builder.journal(Journal::open(&journal_path, resume && !should_create)?);
+
+        let failed_points = failed_points.unwrap_or_else(|| replay::default_path(&collection_name));
+        builder = builder.sink(
+            QdrantSink::new(client, &collection_name)
+                .max_attempts(max_attempts)
+                .failed_points(&failed_points),
+        );
+    }
+
+    let pipeline = builder.embedder(embedder).build()?;
+    let results = pipeline.ingest(&files).await?;
+
+    println!();
+    println!("Summary:");
+    let mut total = 0;
+    let mut unchanged = 0;
+    let mut failed = 0;
+    for (file, result) in files.iter().zip(&results) {
+        match result {
+            Ok(FileOutcome::Uploaded(count)) => {
+                total += count;
+                println!("  ok      {:>6} chunks  {}", count, file.path.display());
+            }
+            Ok(FileOutcome::Unchanged) => {
+                unchanged += 1;
+                println!("  unchanged             {}", file.path.display());
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  failed                {}: {}", file.path.display(), e);
+            }
+        }
+    }
+    let destination = match (&output, output_only) {
+        (Some(output), true) => output.display().to_string(),
+        (Some(output), false) => format!("Qdrant and {}", output.display()),
+        (None, _) => "Qdrant".to_string(),
+    };
+    println!(
+        "Uploaded {} embeddings from {} file(s) to {}, {} file(s) unchanged",
+        total,
+        files.len() - failed - unchanged,
+        destination,
+        unchanged
+    );
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} file(s) failed to ingest", failed, files.len()));
+    }
+    if !output_only {
+        println!("Data uploaded successfully! See it at {}", connection::dashboard_url(&global.connection.url));
+    }
+
+    Ok(())
+}
+
+/// Create the collection, or decide what to do with the existing one according to `--on-existing`.
+///
+/// Returns whether the collection was (re)created.
+async fn prepare_collection(
+    client: &QdrantClient,
+    global: &GlobalArgs,
+    collection_name: &str,
+    on_existing: Option<OnExisting>,
+    resume: bool,
+    embedder: &dyn Embedder,
+    sparse: Option<SparseKind>,
+) -> anyhow::Result<bool> {
+    let mut should_create = true;
+
+    if qdrant::collection_exists(client, collection_name).await? {
+        println!("Collection {} already exists", collection_name);
+        let on_existing = match on_existing {
+            Some(policy) => policy,
+            // Appending is the safe default for unattended runs, and the only way to resume
+            None if global.yes || resume => OnExisting::Append,
+            None => {
+                let question = "Do you want to clear the collection (y), or only add to it (n)?";
+                if prompt::confirm(global, question, true)? {
+                    OnExisting::Recreate
+                } else {
+                    OnExisting::Append
+                }
+            }
+        };
+        match on_existing {
+            OnExisting::Append => {
+                println!("Collection will not be cleared");
+                qdrant::check_vectors(client, collection_name, embedder.dimensions(), sparse.is_some()).await?;
+                if let Some(stored) = qdrant::stored_backend(client, collection_name).await? {
+                    if stored != embedder.backend() {
+                        return Err(anyhow::anyhow!("Collection {} was ingested with backend {}, not {}", collection_name, stored, embedder.backend()));
+                    }
+                }
+                if let Some(stored) = qdrant::stored_model(client, collection_name).await? {
+                    if stored != embedder.model_name() {
+                        return Err(anyhow::anyhow!("Collection {} was ingested with model {}, not {}", collection_name, stored, embedder.model_name()));
+                    }
+                }
+                if let (Some(stored), Some(sparse)) = (qdrant::stored_sparse(client, collection_name).await?, sparse) {
+                    if stored != sparse.name() {
+                        return Err(anyhow::anyhow!("Collection {} stores {} sparse vectors, not {}", collection_name, stored, sparse.name()));
+                    }
+                }
+                should_create = false;
+            }
+            OnExisting::Recreate => {
+                println!("Clearing collection...");
+                client.delete_collection(collection_name).await?;
+                println!("Collection deleted");
+            }
+            OnExisting::Fail => return Err(Failure::CollectionExists(collection_name.to_string()).into()),
+        }
+    }
+
+    if should_create {
+        // Create collection
+        let params = VectorParams {
+            size: embedder.dimensions(),
+            distance: embedder.distance().into(),
+            ..Default::default()
+        };
+        qdrant::create_collection(client, collection_name, params, sparse.is_some()).await?;
+    }
+    qdrant::create_payload_indexes(client, collection_name).await?;
+
+    Ok(should_create)
+}
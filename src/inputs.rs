@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use vectordb::InputFile;
+use walkdir::WalkDir;
+
+/// Resolve the paths given on the command line into the list of files to ingest.
+///
+/// Files are taken as-is, directories are walked recursively and filtered by the include and
+/// exclude globs, patterns containing wildcards are expanded, and `-` reads one path per line from stdin.
+///
+/// The source of a file found in a directory is its path relative to the directory's parent, like
+/// `docs/2023/report.pdf` for `docs`, `./docs` or `/home/me/docs`, so it doesn't depend on where the tool runs from.
+/// Other files keep the path they were given as their source.
+pub fn collect(paths: &[PathBuf], include: &[String], exclude: &[String]) -> anyhow::Result<Vec<InputFile>> {
+    let include = build_glob_set(include)?;
+    let exclude = build_glob_set(exclude)?;
+
+    let mut files = BTreeMap::new();
+    for path in paths {
+        if path.as_os_str() == "-" {
+            for line in std::io::stdin().lock().lines() {
+                let line = line?;
+                let line = line.trim();
+                if !line.is_empty() {
+                    add_path(Path::new(line), &include, &exclude, &mut files)?;
+                }
+            }
+        } else {
+            add_path(path, &include, &exclude, &mut files)?;
+        }
+    }
+
+    let files = files.into_values().collect::<Vec<_>>();
+    check_sources(&files)?;
+    Ok(files)
+}
+
+/// Add the files under the path, keeping the source of a file found earlier.
+fn add_path(path: &Path, include: &GlobSet, exclude: &GlobSet, files: &mut BTreeMap<PathBuf, InputFile>) -> anyhow::Result<()> {
+    if path.is_dir() {
+        // Canonicalize to name `.` and `..`
+        let root = path.canonicalize()?;
+        let root = Path::new(root.file_name().unwrap_or_default());
+        for entry in WalkDir::new(path).follow_links(true) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            if include.is_match(relative) && !exclude.is_match(relative) {
+                let source = root.join(relative);
+                files
+                    .entry(entry.path().to_path_buf())
+                    .or_insert_with(|| InputFile::new(entry.path(), &source));
+            }
+        }
+    } else if path.exists() {
+        files.entry(path.to_path_buf()).or_insert_with(|| InputFile::from(path.to_path_buf()));
+    } else if is_pattern(path) {
+        let pattern = path.to_str().ok_or_else(|| anyhow::anyhow!("Pattern {} is not valid UTF-8", path.display()))?;
+        for entry in glob::glob(pattern)? {
+            let entry = entry?;
+            if entry.is_file() && !exclude.is_match(&entry) {
+                files.entry(entry.clone()).or_insert_with(|| InputFile::from(entry));
+            }
+        }
+    } else {
+        return Err(anyhow::anyhow!("Path {} does not exist", path.display()));
+    }
+    Ok(())
+}
+
+/// Fail if two files have the same source, since each would replace the other's points.
+fn check_sources(files: &[InputFile]) -> anyhow::Result<()> {
+    let mut sources = HashMap::new();
+    for file in files {
+        if let Some(other) = sources.insert(file.source.as_str(), &file.path) {
+            return Err(anyhow::anyhow!(
+                "{} and {} would both be stored as {}; ingest them from directories with different names",
+                other.display(),
+                file.path.display(),
+                file.source
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+fn build_glob_set(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
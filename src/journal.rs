@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::json;
+
+/// Directory the journals are kept in, next to the fastembed cache
+const JOURNAL_DIR: &str = ".ingest_journal";
+
+/// Local record of the batches and files that were uploaded, so an interrupted ingest can be resumed.
+///
+/// Each line is a JSON object: `{"fingerprint": ..., "batch": n, "batch_size": s}` once a batch of a file has been
+/// upserted, and `{"fingerprint": ..., "file": ..., "chunks": n}` once all of its batches have. Entries are keyed by
+/// the file's fingerprint, so they only apply to the same file contents and settings, and batches by their size too,
+/// since a batch number covers other chunks when the embedder's batch size changes.
+pub struct Journal {
+    /// `None` when nothing is uploaded to Qdrant, so there is nothing to resume
+    file: Option<Mutex<File>>,
+    /// Batch size and number of the uploaded batches of each file
+    batches: HashMap<String, HashSet<(usize, usize)>>,
+    files: HashSet<String>,
+}
+
+impl Journal {
+    /// Default location of the journal of a collection.
+    pub fn default_path(collection_name: &str) -> PathBuf {
+        Path::new(JOURNAL_DIR).join(format!("{}.jsonl", collection_name))
+    }
+
+    /// Open the journal, keeping its entries when resuming and starting it over otherwise.
+    pub fn open(path: &Path, resume: bool) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut batches = HashMap::<String, HashSet<(usize, usize)>>::new();
+        let mut files = HashSet::new();
+        if resume {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log::info!("No journal found at {}, starting from the beginning", path.display());
+                    String::new()
+                }
+                Err(e) => return Err(anyhow::anyhow!("Failed to read journal {}: {}", path.display(), e)),
+            };
+            // A line cut short by a crash is skipped
+            for entry in contents.lines().filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok()) {
+                let Some(fingerprint) = entry["fingerprint"].as_str() else {
+                    continue;
+                };
+                if let (Some(batch_size), Some(batch)) = (entry["batch_size"].as_u64(), entry["batch"].as_u64()) {
+                    batches.entry(fingerprint.to_string()).or_default().insert((batch_size as usize, batch as usize));
+                } else if entry["chunks"].is_u64() {
+                    files.insert(fingerprint.to_string());
+                }
+            }
+            log::info!(
+                "Resuming from journal {}: {} file(s) and {} batch(es) already uploaded",
+                path.display(),
+                files.len(),
+                batches.values().map(HashSet::len).sum::<usize>()
+            );
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open journal {}: {}", path.display(), e))?;
+        Ok(Journal { file: Some(Mutex::new(file)), batches, files })
+    }
+
+    /// A journal that records nothing, for runs that only write to `--output`.
+    pub fn disabled() -> Self {
+        Journal { file: None, batches: HashMap::new(), files: HashSet::new() }
+    }
+
+    /// Whether all batches of the file were uploaded by an earlier run.
+    pub fn is_file_done(&self, fingerprint: &str) -> bool {
+        self.files.contains(fingerprint)
+    }
+
+    /// Whether the batch was uploaded by an earlier run with the same batch size.
+    pub fn is_batch_done(&self, fingerprint: &str, batch_size: usize, batch: usize) -> bool {
+        self.batches.get(fingerprint).is_some_and(|batches| batches.contains(&(batch_size, batch)))
+    }
+
+    pub fn batch_done(&self, fingerprint: &str, batch_size: usize, batch: usize) -> anyhow::Result<()> {
+        self.append(json!({ "fingerprint": fingerprint, "batch": batch, "batch_size": batch_size }))
+    }
+
+    pub fn file_done(&self, fingerprint: &str, path: &Path, chunks: usize) -> anyhow::Result<()> {
+        self.append(json!({ "fingerprint": fingerprint, "file": path.display().to_string(), "chunks": chunks }))
+    }
+
+    fn append(&self, entry: serde_json::Value) -> anyhow::Result<()> {
+        if let Some(file) = &self.file {
+            writeln!(file.lock().unwrap(), "{}", entry)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+//! Extract text from documents, split it into chunks, embed the chunks and store them in Qdrant.
+//!
+//! An [`IngestPipeline`] ties together an [`Extractor`], a [`Chunker`], an [`Embedder`] and any number of
+//! [`Sink`]s. The crate provides an implementation of each, wrapping `pdf_extract`, `text_splitter`,
+//! `fastembed` and the Qdrant client, along with an embedder for local ONNX models, embedders for OpenAI-compatible,
+//! Ollama and Text Embeddings Inference APIs and a sink for JSON Lines and Parquet files.
+//!
+//! Progress is reported through the `log` crate: every file's steps at the info level, retries and truncated chunks
+//! as warnings, and the extracted text, chunks and vectors at the debug level.
+
+mod api;
+pub mod cache;
+pub mod chunker;
+pub mod document;
+pub mod embedding;
+pub mod filter;
+pub mod journal;
+mod markdown;
+pub mod ollama;
+pub mod onnx;
+pub mod openai;
+pub mod payload;
+pub mod pipeline;
+pub mod processor;
+pub mod qdrant;
+mod retry;
+mod runtime;
+pub mod sink;
+pub mod sparse;
+pub mod tei;
+
+pub use cache::ModelCache;
+pub use chunker::{ChunkSettings, TextChunker};
+pub use document::{Document, DocumentExtractor};
+pub use embedding::FastEmbedder;
+pub use api::ApiConfig;
+pub use ollama::OllamaEmbedder;
+pub use onnx::{OnnxEmbedder, OnnxOptions};
+pub use openai::OpenAiEmbedder;
+pub use pipeline::{Chunker, Embedder, Extractor, FileOutcome, IngestPipeline, IngestPipelineBuilder, InputFile, Sink};
+pub use qdrant::QdrantSink;
+pub use sink::FileSink;
+pub use tei::TeiEmbedder;
@@ -0,0 +1,34 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Prints the library's progress like the rest of the output: info and debug messages to stdout, warnings and
+/// errors to stderr, so they stay out of `--json` output.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Only the library's own messages, not those of the HTTP and ONNX crates
+        metadata.target().starts_with("vectordb") && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("{}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            Level::Info | Level::Debug | Level::Trace => println!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
+
+/// Print the library's messages, including the extracted text, chunks and vectors with `--debug`.
+pub fn init(debug: bool) {
+    // Only fails if a logger is already set, which then keeps printing
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(if debug { LevelFilter::Debug } else { LevelFilter::Info });
+}
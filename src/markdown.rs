@@ -0,0 +1,139 @@
+/// An ATX (`# Title`) or setext (`Title` underlined with `===` or `---`) heading in a Markdown text.
+struct Heading {
+    offset: usize,
+    level: usize,
+    title: String,
+}
+
+/// Tracks the headings of a Markdown text, to find the section a chunk belongs to.
+pub struct Outline {
+    headings: Vec<Heading>,
+}
+
+impl Outline {
+    pub fn new(text: &str) -> Self {
+        let mut headings = Vec::new();
+        let mut fence: Option<&str> = None;
+        let mut offset = 0;
+        let mut previous: Option<(usize, &str)> = None;
+        for line in text.split_inclusive('\n') {
+            let trimmed = line.trim();
+            let line_offset = offset;
+            offset += line.len();
+
+            // Headings inside fenced code blocks are code, not structure
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                previous = None;
+                continue;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(&trimmed[..3]);
+                previous = None;
+                continue;
+            }
+
+            let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+            let rest = &trimmed[hashes..];
+            if (1..=6).contains(&hashes) && (rest.is_empty() || rest.starts_with(' ')) {
+                let title = rest.trim().trim_end_matches('#').trim();
+                headings.push(Heading { offset: line_offset, level: hashes, title: title.to_string() });
+                previous = None;
+                continue;
+            }
+
+            if let Some((previous_offset, title)) = previous {
+                let level = if !trimmed.is_empty() && trimmed.chars().all(|c| c == '=') {
+                    Some(1)
+                } else if trimmed.len() >= 2 && trimmed.chars().all(|c| c == '-') {
+                    Some(2)
+                } else {
+                    None
+                };
+                if let Some(level) = level {
+                    headings.push(Heading { offset: previous_offset, level, title: title.to_string() });
+                    previous = None;
+                    continue;
+                }
+            }
+            previous = if trimmed.is_empty() { None } else { Some((line_offset, trimmed)) };
+        }
+        Outline { headings }
+    }
+
+    /// The titles of the headings enclosing the given byte offset, joined like "Intro > Setup".
+    ///
+    /// Returns `None` before the first heading.
+    pub fn heading_path(&self, offset: usize) -> Option<String> {
+        let mut path: Vec<&Heading> = Vec::new();
+        for heading in self.headings.iter().take_while(|heading| heading.offset <= offset) {
+            while path.last().is_some_and(|last| last.level >= heading.level) {
+                path.pop();
+            }
+            path.push(heading);
+        }
+        if path.is_empty() {
+            return None;
+        }
+        Some(path.iter().map(|heading| heading.title.as_str()).collect::<Vec<_>>().join(" > "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The heading path at the start of the first line containing `marker`.
+    fn path_at(text: &str, marker: &str) -> Option<String> {
+        Outline::new(text).heading_path(text.find(marker).unwrap())
+    }
+
+    #[test]
+    fn no_path_before_the_first_heading() {
+        let text = "Preface\n\n# Intro\nBody\n";
+        assert_eq!(path_at(text, "Preface"), None);
+        assert_eq!(path_at(text, "Body").as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn setext_headings_have_levels_one_and_two() {
+        let text = "Intro\n=====\n\nSetup\n-----\nInstall it.\n";
+        assert_eq!(path_at(text, "Install").as_deref(), Some("Intro > Setup"));
+    }
+
+    #[test]
+    fn a_rule_after_a_blank_line_is_not_a_heading() {
+        let text = "# Intro\n\n---\nText\n";
+        assert_eq!(path_at(text, "Text").as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn headings_in_fenced_code_are_ignored() {
+        let text = "# Intro\n```sh\n# not a heading\nls\n```\nAfter\n~~~\n## Neither\n~~~\nEnd\n";
+        assert_eq!(path_at(text, "ls").as_deref(), Some("Intro"));
+        assert_eq!(path_at(text, "After").as_deref(), Some("Intro"));
+        assert_eq!(path_at(text, "End").as_deref(), Some("Intro"));
+    }
+
+    #[test]
+    fn closing_hashes_are_not_part_of_the_title() {
+        assert_eq!(path_at("## Setup ##\nText\n", "Text").as_deref(), Some("Setup"));
+    }
+
+    #[test]
+    fn a_shallower_heading_closes_deeper_sections() {
+        let text = "# A\n## B\n### C\nDeep\n## D\nMiddle\n# E\nTop\n";
+        assert_eq!(path_at(text, "Deep").as_deref(), Some("A > B > C"));
+        assert_eq!(path_at(text, "Middle").as_deref(), Some("A > D"));
+        assert_eq!(path_at(text, "Top").as_deref(), Some("E"));
+    }
+
+    #[test]
+    fn skipped_levels_are_left_out() {
+        let text = "# A\n### C\nText\n## B\nMore\n";
+        assert_eq!(path_at(text, "Text").as_deref(), Some("A > C"));
+        assert_eq!(path_at(text, "More").as_deref(), Some("A > B"));
+    }
+}
@@ -0,0 +1,111 @@
+use fastembed::{EmbeddingModel, TextEmbedding};
+use vectordb::cache::ModelCache;
+use vectordb::embedding;
+
+use crate::cli::{GlobalArgs, ModelsArgs, ModelsCommand};
+use crate::dry_run::format_bytes;
+use crate::prompt;
+
+/// Parse a model name as listed by `list-models`.
+fn parse(name: &str) -> anyhow::Result<EmbeddingModel> {
+    embedding::parse_model(name).map_err(|e| anyhow::anyhow!("Invalid model {}: {}", name, e))
+}
+
+/// Print the models fastembed supports, and whether each is in the cache.
+pub fn list_supported(cache: &ModelCache) -> anyhow::Result<()> {
+    println!("{:<26} {:>5}  {:<7} DESCRIPTION", "NAME", "DIM", "CACHED");
+    for info in TextEmbedding::list_supported_models() {
+        println!(
+            "{:<26} {:>5}  {:<7} {}",
+            embedding::model_name(&info.model),
+            info.dim,
+            if cache.is_cached(&info.model) { "yes" } else { "no" },
+            info.description
+        );
+    }
+    Ok(())
+}
+
+pub fn run(args: ModelsArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let cache = global.model_cache();
+    match args.command {
+        ModelsCommand::Download { models } => download(&cache, &models),
+        ModelsCommand::List => list(&cache),
+        ModelsCommand::Prune { keep } => prune(&cache, &keep, global),
+    }
+}
+
+/// Load each model, which downloads the files the cache is missing.
+fn download(cache: &ModelCache, names: &[String]) -> anyhow::Result<()> {
+    if cache.offline {
+        return Err(anyhow::anyhow!("--offline forbids downloading models"));
+    }
+    // Check every name before spending time on the first download
+    let models = names.iter().map(|name| parse(name)).collect::<anyhow::Result<Vec<_>>>()?;
+    for model in models {
+        let name = embedding::model_name(&model);
+        if cache.is_cached(&model) {
+            println!("{} is already cached", name);
+            continue;
+        }
+        println!("Downloading {}", name);
+        embedding::load_model(model, cache)?;
+    }
+    println!("Models are cached in {}", cache.dir.display());
+    Ok(())
+}
+
+fn list(cache: &ModelCache) -> anyhow::Result<()> {
+    let entries = cache.entries()?;
+    if entries.is_empty() {
+        println!("No models cached in {}", cache.dir.display());
+        return Ok(());
+    }
+
+    println!("{:<48} {:>10}  MODELS", "REPOSITORY", "SIZE");
+    for entry in &entries {
+        let models = match (entry.models.is_empty(), entry.known) {
+            (false, _) => entry.models.join(", "),
+            (true, true) => String::from("(incomplete download)"),
+            (true, false) => String::from("(no longer used)"),
+        };
+        println!("{:<48} {:>10}  {}", entry.repository, format_bytes(entry.size as usize), models);
+    }
+    let total = entries.iter().map(|entry| entry.size).sum::<u64>();
+    println!("{} in {}", format_bytes(total as usize), cache.dir.display());
+    Ok(())
+}
+
+/// Remove incomplete and unused repositories, and with `--keep` every repository without a kept model.
+fn prune(cache: &ModelCache, keep: &[String], global: &GlobalArgs) -> anyhow::Result<()> {
+    let keep = keep
+        .iter()
+        .map(|name| parse(name).map(|model| embedding::model_name(&model)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let stale = cache
+        .entries()?
+        .into_iter()
+        .filter(|entry| entry.models.is_empty() || (!keep.is_empty() && !entry.models.iter().any(|model| keep.contains(model))))
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        println!("Nothing to prune in {}", cache.dir.display());
+        return Ok(());
+    }
+
+    for entry in &stale {
+        println!("  {:<48} {:>10}", entry.repository, format_bytes(entry.size as usize));
+    }
+    let total = stale.iter().map(|entry| entry.size).sum::<u64>();
+    let question = format!("Do you want to remove {} model(s), freeing {}?", stale.len(), format_bytes(total as usize));
+    // `--yes` confirms the pruning the user explicitly asked for
+    if !global.yes && !prompt::confirm(global, &question, false)? {
+        println!("Nothing was removed");
+        return Ok(());
+    }
+    for entry in &stale {
+        std::fs::remove_dir_all(&entry.path)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", entry.path.display(), e))?;
+    }
+    println!("Removed {} model(s), freeing {}", stale.len(), format_bytes(total as usize));
+    Ok(())
+}
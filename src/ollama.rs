@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::api::{self, ApiConfig};
+use crate::embedding::{self, Backend};
+use crate::pipeline::Embedder;
+
+/// Default address of a local Ollama server
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+/// Default model
+pub const DEFAULT_MODEL: &str = "nomic-embed-text";
+
+/// Embeds texts with the `/api/embed` endpoint of an Ollama server.
+pub struct OllamaEmbedder {
+    client: reqwest::Client,
+    url: Url,
+    model: String,
+    config: ApiConfig,
+    dimensions: u64,
+}
+
+impl OllamaEmbedder {
+    /// Check the server by embedding a short text, which also tells the size of the model's vectors.
+    pub async fn connect(config: ApiConfig) -> anyhow::Result<Self> {
+        let mut embedder = OllamaEmbedder {
+            client: api::client()?,
+            url: config.endpoint(&["api", "embed"])?,
+            model: config.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            config,
+            dimensions: 0,
+        };
+
+        let vectors = embedder
+            .request(embedding::PROBE)
+            .await
+            .map_err(|e| anyhow::anyhow!("{:#}\nIs the model pulled? Try `ollama pull {}`", e, embedder.model))?;
+        embedder.dimensions = embedding::probed_dimensions(&embedder.url, vectors)?;
+        Ok(embedder)
+    }
+
+    async fn request(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        // Longer inputs are cut to the model's context length, like fastembed does
+        let body = json!({
+            "model": self.model,
+            "input": texts,
+            "truncate": true,
+        });
+        let mut request = self.client.post(self.url.clone()).json(&body);
+        // Ollama has no authentication of its own, but is often put behind a proxy that checks a bearer token
+        if let Some(api_key) = &self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = api::send(request, self.config.max_attempts).await?;
+        api::parse_vectors(&response["embeddings"], texts.len())
+    }
+}
+
+#[async_trait]
+impl Embedder for OllamaEmbedder {
+    fn backend(&self) -> String {
+        Backend::Ollama.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn batch_size(&self) -> usize {
+        self.config.texts_per_call()
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        api::embed_batches(texts, &self.config, |batch| self.request(batch)).await
+    }
+}
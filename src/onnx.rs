@@ -0,0 +1,365 @@
+use std::path::{Path, PathBuf};
+use std::thread::available_parallelism;
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use ndarray::{Array1, Array2, ArrayView2, ArrayView3, Axis, Ix2, Ix3};
+use ort::{GraphOptimizationLevel, Session, Value};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+use crate::embedding::{self, Backend};
+use crate::pipeline::Embedder;
+use crate::runtime;
+
+/// Inputs are truncated to this many tokens unless the model's tokenizer config allows fewer, like fastembed does
+pub const DEFAULT_MAX_LENGTH: usize = 512;
+
+/// How the token vectors of a text are combined into one vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Pooling {
+    /// The vector of the first token, like fastembed's BGE models
+    Cls,
+    /// The average of the vectors of all tokens, like most sentence-transformers models
+    Mean,
+    /// The vector of the last token, for decoder models
+    Last,
+}
+
+/// Where to find a local model and how to run it.
+#[derive(Debug, Clone)]
+pub struct OnnxOptions {
+    /// Directory with `model.onnx` (or `onnx/model.onnx`) and `tokenizer.json`
+    pub dir: PathBuf,
+    /// Name recorded in the payload, or the directory's name when `None`
+    pub name: Option<String>,
+    /// Pooling, or the one in a sentence-transformers `1_Pooling/config.json` when `None`, falling back to `cls`
+    pub pooling: Option<Pooling>,
+    /// Whether to scale vectors to unit length, or whether `modules.json` has a `Normalize` module when `None`,
+    /// falling back to `true`
+    pub normalize: Option<bool>,
+    /// Number of tokens inputs are truncated to
+    pub max_length: Option<usize>,
+}
+
+/// Embeds texts with an ONNX model and tokenizer read from a local directory, without network access.
+///
+/// fastembed's user-defined models always take the first token's vector and normalize it, so the model is run
+/// here with the same ONNX runtime to let fine-tuned models use their own pooling.
+pub struct OnnxEmbedder {
+    name: String,
+    session: Session,
+    tokenizer: Tokenizer,
+    measuring_tokenizer: Tokenizer,
+    needs_token_type_ids: bool,
+    pooling: Pooling,
+    normalize: bool,
+    max_length: usize,
+    dimensions: u64,
+}
+
+impl OnnxEmbedder {
+    /// Load the model and embed a short text, which tells the size of its vectors.
+    pub fn new(options: OnnxOptions) -> anyhow::Result<Self> {
+        let dir = &options.dir;
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("Model directory {} does not exist", dir.display()));
+        }
+        let model_file = [dir.join("model.onnx"), dir.join("onnx").join("model.onnx")]
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| anyhow::anyhow!("No model.onnx in {} or its onnx directory", dir.display()))?;
+        let tokenizer_file = dir.join("tokenizer.json");
+        if !tokenizer_file.is_file() {
+            return Err(anyhow::anyhow!("No tokenizer.json in {}", dir.display()));
+        }
+
+        let tokenizer_config = read_json(&dir.join("tokenizer_config.json"))?;
+        let max_length = options.max_length.unwrap_or_else(|| {
+            // Tokenizers without a limit store a huge number, which does not fit in a u64
+            let model_max_length = tokenizer_config["model_max_length"].as_f64().unwrap_or(f64::MAX);
+            DEFAULT_MAX_LENGTH.min(model_max_length as usize)
+        });
+        let measuring_tokenizer = Tokenizer::from_file(&tokenizer_file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", tokenizer_file.display(), e))?;
+        let tokenizer = input_tokenizer(measuring_tokenizer.clone(), &tokenizer_config, max_length)?;
+        let measuring_tokenizer = {
+            let mut tokenizer = measuring_tokenizer;
+            tokenizer.with_truncation(None).map_err(anyhow::Error::msg)?;
+            tokenizer.with_padding(None);
+            tokenizer
+        };
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_intra_threads(available_parallelism()?.get())?
+            .commit_from_file(&model_file)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", model_file.display(), e))?;
+        let needs_token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+
+        let name = match options.name {
+            Some(name) => name,
+            None => dir
+                .canonicalize()?
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| anyhow::anyhow!("Cannot name the model in {}, give it a name with --model", dir.display()))?,
+        };
+        let mut embedder = OnnxEmbedder {
+            name,
+            session,
+            tokenizer,
+            measuring_tokenizer,
+            needs_token_type_ids,
+            pooling: resolve_pooling(options.pooling, dir)?,
+            normalize: options.normalize.or(configured_normalize(dir)?).unwrap_or(true),
+            max_length,
+            dimensions: 0,
+        };
+        let vectors = embedder.run(embedding::PROBE)?;
+        embedder.dimensions = embedding::probed_dimensions(&embedder.name, vectors)?;
+        Ok(embedder)
+    }
+
+    pub fn pooling(&self) -> Pooling {
+        self.pooling
+    }
+
+    pub fn normalizes(&self) -> bool {
+        self.normalize
+    }
+
+    fn run(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true).map_err(anyhow::Error::msg)?;
+        let length = encodings.first().map_or(0, |encoding| encoding.len());
+        let shape = (encodings.len(), length);
+        let ids = Array2::from_shape_vec(shape, encodings.iter().flat_map(|e| e.get_ids().iter().map(|&id| id as i64)).collect())?;
+        let mask = Array2::from_shape_vec(
+            shape,
+            encodings.iter().flat_map(|e| e.get_attention_mask().iter().map(|&m| m as i64)).collect(),
+        )?;
+        let type_ids = Array2::from_shape_vec(shape, encodings.iter().flat_map(|e| e.get_type_ids().iter().map(|&id| id as i64)).collect())?;
+
+        let mut inputs = ort::inputs![
+            "input_ids" => Value::from_array(ids)?,
+            "attention_mask" => Value::from_array(mask.clone())?,
+        ]?;
+        if self.needs_token_type_ids {
+            inputs.push(("token_type_ids".into(), Value::from_array(type_ids)?.into()));
+        }
+        let outputs = self.session.run(inputs)?;
+
+        // Models exported with several outputs name the token vectors `last_hidden_state`
+        let key = match outputs.len() {
+            1 => outputs.keys().next().expect("one output"),
+            _ => "last_hidden_state",
+        };
+        let output = outputs[key].try_extract_tensor::<f32>()?;
+        let vectors = match output.ndim() {
+            // Some exports already pool inside the model
+            2 => output.into_dimensionality::<Ix2>()?.rows().into_iter().map(|row| row.to_vec()).collect(),
+            3 => pool(output.into_dimensionality::<Ix3>()?, mask.view(), self.pooling),
+            n => return Err(anyhow::anyhow!("{} returned a tensor with {} dimensions, expected 2 or 3", self.name, n)),
+        };
+        Ok(if self.normalize { vectors.into_iter().map(|vector| normalize(&vector)).collect() } else { vectors })
+    }
+}
+
+#[async_trait]
+impl Embedder for OnnxEmbedder {
+    fn backend(&self) -> String {
+        Backend::Onnx.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn tokenizer(&self) -> Option<&Tokenizer> {
+        Some(&self.measuring_tokenizer)
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        Some(self.max_length)
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        runtime::blocking(|| self.run(texts))
+    }
+}
+
+/// The tokenizer with the padding and truncation the model's inputs need, keeping any padding `tokenizer.json`
+/// already sets.
+fn input_tokenizer(mut tokenizer: Tokenizer, tokenizer_config: &serde_json::Value, max_length: usize) -> anyhow::Result<Tokenizer> {
+    if tokenizer.get_padding().is_none() {
+        let pad_token = tokenizer_config["pad_token"].as_str().unwrap_or("[PAD]").to_string();
+        let pad_id = tokenizer.token_to_id(&pad_token).unwrap_or(0);
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            pad_id,
+            pad_token,
+            ..Default::default()
+        }));
+    }
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length,
+            ..Default::default()
+        }))
+        .map_err(anyhow::Error::msg)?;
+    Ok(tokenizer)
+}
+
+/// Combine the token vectors of each text, skipping padding.
+fn pool(output: ArrayView3<f32>, mask: ArrayView2<i64>, pooling: Pooling) -> Vec<Vec<f32>> {
+    output
+        .outer_iter()
+        .zip(mask.outer_iter())
+        .map(|(tokens, mask)| match pooling {
+            Pooling::Cls => tokens.row(0).to_vec(),
+            Pooling::Mean => {
+                let count = mask.iter().filter(|&&m| m != 0).count().max(1) as f32;
+                let sum = tokens
+                    .outer_iter()
+                    .zip(mask.iter())
+                    .filter(|(_, m)| **m != 0)
+                    .fold(Array1::<f32>::zeros(tokens.len_of(Axis(1))), |sum, (token, _)| sum + token);
+                (sum / count).to_vec()
+            }
+            Pooling::Last => {
+                let last = mask.iter().rposition(|&m| m != 0).unwrap_or(0);
+                tokens.row(last).to_vec()
+            }
+        })
+        .collect()
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    // Avoid dividing by zero for an all-zero vector
+    vector.iter().map(|value| value / (norm + 1e-12)).collect()
+}
+
+/// The pooling given on the command line, else the one the model directory configures, else `cls`.
+fn resolve_pooling(pooling: Option<Pooling>, dir: &Path) -> anyhow::Result<Pooling> {
+    Ok(pooling.or(configured_pooling(dir)?).unwrap_or(Pooling::Cls))
+}
+
+/// The pooling of a sentence-transformers export, from its `1_Pooling/config.json`.
+fn configured_pooling(dir: &Path) -> anyhow::Result<Option<Pooling>> {
+    let config = read_json(&dir.join("1_Pooling").join("config.json"))?;
+    let modes = [
+        ("pooling_mode_cls_token", Pooling::Cls),
+        ("pooling_mode_mean_tokens", Pooling::Mean),
+        ("pooling_mode_lasttoken", Pooling::Last),
+    ];
+    Ok(modes
+        .into_iter()
+        .find(|(key, _)| config[key].as_bool() == Some(true))
+        .map(|(_, pooling)| pooling))
+}
+
+/// Whether a sentence-transformers export normalizes its vectors, from the modules listed in its `modules.json`.
+fn configured_normalize(dir: &Path) -> anyhow::Result<Option<bool>> {
+    let modules = read_json(&dir.join("modules.json"))?;
+    Ok(modules.as_array().map(|modules| {
+        modules
+            .iter()
+            .any(|module| module["type"].as_str().is_some_and(|kind| kind.ends_with("Normalize")))
+    }))
+}
+
+/// Parse an optional JSON file of the model directory, `null` if it does not exist.
+fn read_json(path: &Path) -> anyhow::Result<serde_json::Value> {
+    if !path.is_file() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_slice(&std::fs::read(path)?).map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array3};
+
+    use super::*;
+
+    /// Two texts of three tokens with two dimensions, the second padded after its first token.
+    fn output() -> (Array3<f32>, Array2<i64>) {
+        let output = array![
+            [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]],
+            [[2.0, 0.0], [100.0, 100.0], [100.0, 100.0]],
+        ];
+        (output, array![[1, 1, 1], [1, 0, 0]])
+    }
+
+    /// A fresh directory for the test's files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vectordb-onnx-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn write_pooling_config(dir: &Path, config: serde_json::Value) {
+        std::fs::create_dir_all(dir.join("1_Pooling")).unwrap();
+        std::fs::write(dir.join("1_Pooling").join("config.json"), config.to_string()).unwrap();
+    }
+
+    #[test]
+    fn mean_pooling_skips_padding() {
+        let (output, mask) = output();
+        assert_eq!(pool(output.view(), mask.view(), Pooling::Mean), vec![vec![3.0, 4.0], vec![2.0, 0.0]]);
+    }
+
+    #[test]
+    fn cls_pooling_takes_first_token() {
+        let (output, mask) = output();
+        assert_eq!(pool(output.view(), mask.view(), Pooling::Cls), vec![vec![1.0, 2.0], vec![2.0, 0.0]]);
+    }
+
+    #[test]
+    fn last_pooling_takes_last_unpadded_token() {
+        let (output, mask) = output();
+        assert_eq!(pool(output.view(), mask.view(), Pooling::Last), vec![vec![5.0, 6.0], vec![2.0, 0.0]]);
+    }
+
+    #[test]
+    fn normalize_scales_to_unit_length() {
+        let vector = normalize(&[3.0, 4.0]);
+        assert!((vector[0] - 0.6).abs() < 1e-6);
+        assert!((vector[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalize_keeps_zero_vector() {
+        assert_eq!(normalize(&[0.0, 0.0, 0.0]), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn pooling_from_config() {
+        let dir = temp_dir("config");
+        write_pooling_config(&dir, serde_json::json!({"pooling_mode_cls_token": false, "pooling_mode_mean_tokens": true}));
+        assert_eq!(configured_pooling(&dir).unwrap(), Some(Pooling::Mean));
+        assert_eq!(resolve_pooling(None, &dir).unwrap(), Pooling::Mean);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pooling_option_overrides_config() {
+        let dir = temp_dir("override");
+        write_pooling_config(&dir, serde_json::json!({"pooling_mode_mean_tokens": true}));
+        assert_eq!(resolve_pooling(Some(Pooling::Last), &dir).unwrap(), Pooling::Last);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pooling_defaults_to_cls() {
+        let dir = temp_dir("default");
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(configured_pooling(&dir).unwrap(), None);
+        assert_eq!(resolve_pooling(None, &dir).unwrap(), Pooling::Cls);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+use async_trait::async_trait;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::api::{self, ApiConfig};
+use crate::embedding::{self, Backend};
+use crate::pipeline::Embedder;
+
+/// Default base URL of the API
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+/// Default model
+pub const DEFAULT_MODEL: &str = "text-embedding-3-small";
+
+/// Embeds texts with an OpenAI-compatible `/embeddings` endpoint, like those of OpenAI, Azure OpenAI,
+/// llama.cpp or vLLM.
+pub struct OpenAiEmbedder {
+    client: reqwest::Client,
+    url: Url,
+    model: String,
+    config: ApiConfig,
+    dimensions: u64,
+}
+
+impl OpenAiEmbedder {
+    /// Check the API by embedding a short text, which also tells the size of the model's vectors.
+    pub async fn connect(config: ApiConfig) -> anyhow::Result<Self> {
+        let mut embedder = OpenAiEmbedder {
+            client: api::client()?,
+            url: config.endpoint(&["embeddings"])?,
+            model: config.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            config,
+            dimensions: 0,
+        };
+
+        let vectors = embedder.request(embedding::PROBE).await?;
+        embedder.dimensions = embedding::probed_dimensions(&embedder.url, vectors)?;
+        if let Some(requested) = embedder.config.dimensions {
+            if requested != embedder.dimensions {
+                return Err(anyhow::anyhow!(
+                    "{} returned vectors of size {} instead of the requested {}",
+                    embedder.model,
+                    embedder.dimensions,
+                    requested
+                ));
+            }
+        }
+        Ok(embedder)
+    }
+
+    /// Azure OpenAI expects the key in an `api-key` header rather than as a bearer token. Its endpoints are on
+    /// `azure.com`, or behind a gateway that keeps their `/openai/deployments/` path.
+    fn is_azure(&self) -> bool {
+        let on_azure = self.url.host_str().is_some_and(|host| host.ends_with(".azure.com"));
+        on_azure || self.url.path().starts_with("/openai/deployments/")
+    }
+
+    async fn request(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut body = json!({
+            "model": self.model,
+            "input": texts,
+            "encoding_format": "float",
+        });
+        if let Some(dimensions) = self.config.dimensions {
+            body["dimensions"] = dimensions.into();
+        }
+        let mut request = self.client.post(self.url.clone()).json(&body);
+        if let Some(api_key) = &self.config.api_key {
+            request = if self.is_azure() {
+                request.header("api-key", api_key)
+            } else {
+                request.bearer_auth(api_key)
+            };
+        }
+        parse_embeddings(api::send(request, self.config.max_attempts).await?, texts.len())
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    fn backend(&self) -> String {
+        Backend::Openai.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn batch_size(&self) -> usize {
+        self.config.texts_per_call()
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        api::embed_batches(texts, &self.config, |batch| self.request(batch)).await
+    }
+}
+
+/// The vectors of a response, in the order of the inputs.
+fn parse_embeddings(response: serde_json::Value, count: usize) -> anyhow::Result<Vec<Vec<f32>>> {
+    let data = response["data"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Unexpected response without a data array"))?;
+    let mut embeddings = vec![None; count];
+    for (position, item) in data.iter().enumerate() {
+        let index = item["index"].as_u64().map_or(position, |index| index as usize);
+        let embedding = serde_json::from_value::<Vec<f32>>(item["embedding"].clone())
+            .map_err(|e| anyhow::anyhow!("Unexpected embedding in response: {}", e))?;
+        *embeddings
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("Response has an embedding for input {} of {}", index, count))? = Some(embedding);
+    }
+    embeddings
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow::anyhow!("Response is missing embeddings, expected {}", count))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// A local HTTP server that answers each request with the next of its responses, and records the requests.
+    struct MockServer {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        fn start(responses: Vec<String>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&requests);
+            std::thread::spawn(move || {
+                for (stream, response) in listener.incoming().zip(responses) {
+                    let mut stream = stream.unwrap();
+                    let request = read_request(&mut stream);
+                    recorded.lock().unwrap().push(request);
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+            MockServer { url, requests }
+        }
+
+        /// The requests received so far, with lowercase header names and values.
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().iter().map(|request| request.to_lowercase()).collect()
+        }
+    }
+
+    fn read_request(stream: &mut std::net::TcpStream) -> String {
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        request.push_str(&String::from_utf8(body).unwrap());
+        request
+    }
+
+    fn response(status: &str, headers: &[&str], body: serde_json::Value) -> String {
+        let body = body.to_string();
+        let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        response.push_str("Content-Type: application/json\r\n");
+        for header in headers {
+            response.push_str(&format!("{}\r\n", header));
+        }
+        response.push_str("\r\n");
+        response + &body
+    }
+
+    fn embeddings(vectors: &[(usize, [f32; 2])]) -> String {
+        let data = vectors
+            .iter()
+            .map(|(index, embedding)| json!({ "index": index, "embedding": embedding }))
+            .collect::<Vec<_>>();
+        response("200 OK", &[], json!({ "data": data }))
+    }
+
+    fn config(base_url: &str) -> ApiConfig {
+        ApiConfig {
+            base_url: base_url.to_string(),
+            model: None,
+            api_key: Some(String::from("secret")),
+            dimensions: None,
+            batch_size: 64,
+            concurrency: 1,
+            max_attempts: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn embeddings_are_ordered_by_index() {
+        let server = MockServer::start(vec![
+            embeddings(&[(0, [0.0, 0.0])]),
+            embeddings(&[(2, [3.0, 3.0]), (0, [1.0, 1.0]), (1, [2.0, 2.0])]),
+        ]);
+        let embedder = OpenAiEmbedder::connect(config(&server.url)).await.unwrap();
+        assert_eq!(embedder.dimensions(), 2);
+
+        let vectors = embedder.embed(&["a", "b", "c"]).await.unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 1.0], vec![2.0, 2.0], vec![3.0, 3.0]]);
+        let requests = server.requests();
+        assert!(requests[1].starts_with("post /embeddings "));
+        assert!(requests[1].contains(r#""input":["a","b","c"]"#));
+        assert!(requests[1].contains("authorization: bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn rate_limits_wait_for_retry_after() {
+        let server = MockServer::start(vec![
+            response("429 Too Many Requests", &["Retry-After: 1"], json!({ "error": { "message": "slow down" } })),
+            embeddings(&[(0, [0.0, 0.0])]),
+        ]);
+        let start = Instant::now();
+        let config = ApiConfig { max_attempts: 2, ..config(&server.url) };
+        OpenAiEmbedder::connect(config).await.unwrap();
+        // The backoff before a second attempt is at most half a second
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn other_dimensions_than_requested_are_rejected() {
+        let server = MockServer::start(vec![embeddings(&[(0, [0.0, 0.0])])]);
+        let config = ApiConfig { dimensions: Some(3), ..config(&server.url) };
+        let error = OpenAiEmbedder::connect(config).await.err().unwrap();
+        assert!(error.to_string().contains("size 2 instead of the requested 3"), "{}", error);
+        assert!(server.requests()[0].contains(r#""dimensions":3"#));
+    }
+
+    #[tokio::test]
+    async fn azure_gets_the_key_in_an_api_key_header() {
+        let server = MockServer::start(vec![embeddings(&[(0, [0.0, 0.0])])]);
+        let base_url = format!("{}/openai/deployments/embeddings?api-version=2024-02-01", server.url);
+        OpenAiEmbedder::connect(config(&base_url)).await.unwrap();
+        let request = &server.requests()[0];
+        assert!(request.starts_with("post /openai/deployments/embeddings/embeddings?api-version=2024-02-01 "));
+        assert!(request.contains("api-key: secret"));
+        assert!(!request.contains("authorization:"));
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::{PointId, Value};
+
+/// Convert a payload returned by Qdrant back into plain JSON.
+pub fn to_json(payload: HashMap<String, Value>) -> serde_json::Value {
+    serde_json::Value::Object(payload.into_iter().map(|(key, value)| (key, value_to_json(value))).collect())
+}
+
+fn value_to_json(value: Value) -> serde_json::Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::BoolValue(b)) => b.into(),
+        Some(Kind::IntegerValue(i)) => i.into(),
+        Some(Kind::DoubleValue(d)) => d.into(),
+        Some(Kind::StringValue(s)) => s.into(),
+        Some(Kind::ListValue(list)) => list.values.into_iter().map(value_to_json).collect(),
+        Some(Kind::StructValue(s)) => to_json(s.fields),
+    }
+}
+
+/// Convert a point ID into a JSON number or UUID string.
+pub fn point_id_to_json(id: Option<PointId>) -> serde_json::Value {
+    match id.and_then(|id| id.point_id_options) {
+        Some(PointIdOptions::Num(num)) => num.into(),
+        Some(PointIdOptions::Uuid(uuid)) => uuid.into(),
+        None => serde_json::Value::Null,
+    }
+}
+
+/// Interpret a command-line value as a JSON integer, float or boolean where possible, and a string otherwise.
+///
+/// Wrapping the value in double quotes forces it to be a string.
+pub fn parse_scalar(value: &str) -> serde_json::Value {
+    if let Some(quoted) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return quoted.into();
+    }
+    if let Ok(i) = value.parse::<i64>() {
+        return i.into();
+    }
+    if let Ok(f) = value.parse::<f64>() {
+        if f.is_finite() {
+            return f.into();
+        }
+    }
+    match value {
+        "true" => true.into(),
+        "false" => false.into(),
+        _ => value.into(),
+    }
+}
@@ -0,0 +1,504 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use qdrant_client::prelude::*;
+use tokenizers::Tokenizer;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::document::{Document, DocumentExtractor};
+use crate::journal::Journal;
+use crate::processor::{Payloads, Processor};
+use crate::sparse::{SparseEncoder, SparseKind};
+
+/// Number of chunks embedded and stored together, unless the embedder asks for another number
+pub const BATCH_SIZE: usize = 64;
+/// Number of embedded batches per job waiting to be stored before embedding pauses
+const UPLOAD_QUEUE: usize = 2;
+
+/// Turns the contents of a file into text.
+pub trait Extractor: Send + Sync {
+    /// Name recorded in every file's fingerprint, so files are processed again when the extractor changes.
+    fn name(&self) -> String;
+
+    fn extract(&self, path: &Path, bytes: &[u8]) -> anyhow::Result<Document>;
+}
+
+/// Splits the text of a document into chunks.
+pub trait Chunker: Send + Sync {
+    /// The chunks of the document, each with its byte offset into the document's text.
+    fn chunks<'a>(&self, document: &'a Document) -> Vec<(usize, &'a str)>;
+
+    /// Whether the document is split along Markdown structure, so its chunks get a `heading_path`.
+    fn is_markdown(&self, document: &Document) -> bool {
+        document.markdown
+    }
+
+    /// The settings recorded in the `chunking` field of every payload.
+    fn settings(&self) -> serde_json::Value;
+}
+
+/// Computes the dense vectors of chunks.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Name of the backend, recorded in the `embedding_backend` field of every payload.
+    fn backend(&self) -> String;
+
+    /// Name recorded in the `embedding_model` field of every payload.
+    fn model_name(&self) -> String;
+
+    /// Size of the vectors.
+    fn dimensions(&self) -> u64;
+
+    /// Distance the vectors are meant to be compared with.
+    fn distance(&self) -> Distance {
+        Distance::Cosine
+    }
+
+    /// The model's tokenizer without truncation or padding, when it runs locally, for measuring chunks.
+    fn tokenizer(&self) -> Option<&Tokenizer> {
+        None
+    }
+
+    /// Number of tokens the model reads before truncating its input, including its special tokens, when known.
+    fn max_tokens(&self) -> Option<usize> {
+        None
+    }
+
+    /// Number of chunks to pass to each call of [`Embedder::embed`], which are then stored together.
+    fn batch_size(&self) -> usize {
+        BATCH_SIZE
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+#[async_trait]
+impl Embedder for Box<dyn Embedder> {
+    fn backend(&self) -> String {
+        (**self).backend()
+    }
+
+    fn model_name(&self) -> String {
+        (**self).model_name()
+    }
+
+    fn dimensions(&self) -> u64 {
+        (**self).dimensions()
+    }
+
+    fn distance(&self) -> Distance {
+        (**self).distance()
+    }
+
+    fn tokenizer(&self) -> Option<&Tokenizer> {
+        (**self).tokenizer()
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        (**self).max_tokens()
+    }
+
+    fn batch_size(&self) -> usize {
+        (**self).batch_size()
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        (**self).embed(texts).await
+    }
+}
+
+/// Stores the points of a pipeline.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    async fn write(&self, points: &[PointStruct]) -> anyhow::Result<()>;
+
+    /// Whether all points of the file with this fingerprint are stored already, so it can be skipped.
+    async fn is_stored(&self, _fingerprint: &str) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Remove the points of other versions of the file with this source, before those of this version are written.
+    async fn remove_other_versions(&self, _source: &str, _fingerprint: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once every point has been written.
+    async fn finish(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A file to ingest, and the identity its points are stored under.
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Recorded in the `source` field of every payload. A new version of a file replaces the points with the same
+    /// source, so it must tell apart files with the same name in different directories.
+    pub source: String,
+}
+
+impl InputFile {
+    /// A file whose source is the given path, with `/` separators and without `.` components.
+    pub fn new(path: impl Into<PathBuf>, source: &Path) -> Self {
+        InputFile {
+            path: path.into(),
+            source: source_name(source),
+        }
+    }
+}
+
+impl From<PathBuf> for InputFile {
+    /// A file whose source is its own path.
+    fn from(path: PathBuf) -> Self {
+        let source = source_name(&path);
+        InputFile { path, source }
+    }
+}
+
+/// The path with `/` separators and without `.` components, so the same file gets the same source on every platform.
+fn source_name(path: &Path) -> String {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .map(|component| match component {
+            // Joined with the next component, this makes the leading `/` of an absolute path
+            Component::RootDir => String::new(),
+            component => component.as_os_str().to_string_lossy().into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// What became of a file given to [`IngestPipeline::ingest_file`].
+pub enum FileOutcome {
+    /// The file was (re-)ingested with the given number of chunks
+    Uploaded(usize),
+    /// The file was already ingested with the same contents and settings
+    Unchanged,
+}
+
+/// Builds an [`IngestPipeline`].
+///
+/// A chunker and an embedder are required. Files are read with [`DocumentExtractor`] unless another
+/// extractor is given, and no journal is kept unless one is given.
+#[derive(Default)]
+pub struct IngestPipelineBuilder {
+    extractor: Option<Box<dyn Extractor>>,
+    chunker: Option<Box<dyn Chunker>>,
+    embedder: Option<Box<dyn Embedder>>,
+    sparse: Option<(SparseKind, SparseEncoder)>,
+    sinks: Vec<Arc<dyn Sink>>,
+    journal: Option<Journal>,
+    metadata: Vec<(String, serde_json::Value)>,
+    jobs: usize,
+}
+
+impl IngestPipelineBuilder {
+    pub fn extractor(mut self, extractor: impl Extractor + 'static) -> Self {
+        self.extractor = Some(Box::new(extractor));
+        self
+    }
+
+    pub fn chunker(mut self, chunker: impl Chunker + 'static) -> Self {
+        self.chunker = Some(Box::new(chunker));
+        self
+    }
+
+    pub fn embedder(mut self, embedder: impl Embedder + 'static) -> Self {
+        self.embedder = Some(Box::new(embedder));
+        self
+    }
+
+    /// Also compute sparse vectors, stored next to the dense vectors.
+    pub fn sparse(mut self, kind: SparseKind, encoder: SparseEncoder) -> Self {
+        self.sparse = Some((kind, encoder));
+        self
+    }
+
+    /// Add a destination for the points. Every batch is written to each sink in turn.
+    pub fn sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Record the stored batches and files, and skip those an earlier run recorded.
+    pub fn journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Extra fields added to every payload, never overriding the built-in ones.
+    pub fn metadata(mut self, metadata: Vec<(String, serde_json::Value)>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Number of files [`IngestPipeline::ingest`] processes at once.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Build only the stages that turn files into points, to look at what an ingest would produce.
+    pub fn build_processor(self) -> anyhow::Result<Processor> {
+        let extractor = self.extractor.unwrap_or_else(|| Box::new(DocumentExtractor));
+        let chunker = self.chunker.ok_or_else(|| anyhow::anyhow!("The pipeline needs a chunker"))?;
+        let embedder = self.embedder.ok_or_else(|| anyhow::anyhow!("The pipeline needs an embedder"))?;
+
+        let chunking = chunker.settings();
+        let mut settings = format!("extractor={};chunking={};model={}", extractor.name(), chunking, embedder.model_name());
+        if let Some((sparse, _)) = &self.sparse {
+            settings.push_str(&format!(";sparse={}", sparse.name()));
+        }
+        Ok(Processor {
+            extractor,
+            chunker,
+            embedder,
+            sparse: self.sparse,
+            chunking,
+            metadata: self.metadata,
+            settings,
+        })
+    }
+
+    /// Build the pipeline and start the task that writes its batches to the sinks.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn build(mut self) -> anyhow::Result<IngestPipeline> {
+        let sinks = std::mem::take(&mut self.sinks);
+        let journal = Arc::new(self.journal.take().unwrap_or_else(Journal::disabled));
+        let jobs = self.jobs.max(1);
+        let processor = self.build_processor()?;
+
+        // One task stores the batches embedded by every file
+        let (uploads, receiver) = mpsc::channel(UPLOAD_QUEUE * jobs);
+        let uploader = tokio::spawn(upload(sinks.clone(), Arc::clone(&journal), receiver));
+        Ok(IngestPipeline {
+            processor,
+            sinks,
+            journal,
+            jobs,
+            uploads,
+            uploader,
+        })
+    }
+}
+
+/// Extracts, chunks and embeds files, and writes their points to the sinks.
+///
+/// Chunks are embedded in batches, and each batch is handed to a shared task that writes it to the sinks while the
+/// next one is embedded, so only a few batches of embeddings are held in memory at a time.
+///
+/// ```ignore
+/// let pipeline = IngestPipeline::builder()
+///     .chunker(TextChunker::new(&settings, None)?)
+///     .embedder(FastEmbedder::new(EmbeddingModel::AllMiniLML6V2, &ModelCache::default())?)
+///     .sink(QdrantSink::new(client, "docs"))
+///     .build()?;
+/// let outcomes = pipeline.ingest(&files).await?;
+/// ```
+pub struct IngestPipeline {
+    processor: Processor,
+    sinks: Vec<Arc<dyn Sink>>,
+    journal: Arc<Journal>,
+    jobs: usize,
+    /// Queue of the shared upload task
+    uploads: mpsc::Sender<Upload>,
+    uploader: JoinHandle<anyhow::Result<()>>,
+}
+
+impl IngestPipeline {
+    pub fn builder() -> IngestPipelineBuilder {
+        IngestPipelineBuilder::default()
+    }
+
+    pub fn processor(&self) -> &Processor {
+        &self.processor
+    }
+
+    /// Ingest the files, at most `jobs` at a time, then finish the sinks.
+    ///
+    /// Returns the outcome of every file, in order; a file that fails doesn't stop the others.
+    pub async fn ingest(self, files: &[InputFile]) -> anyhow::Result<Vec<anyhow::Result<FileOutcome>>> {
+        let pipeline = Arc::new(self);
+        // Files are processed on the runtime's worker threads
+        let permits = Arc::new(Semaphore::new(pipeline.jobs));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let file_count = files.len();
+        let tasks = files
+            .iter()
+            .map(|file| {
+                let pipeline = Arc::clone(&pipeline);
+                let permits = Arc::clone(&permits);
+                let completed = Arc::clone(&completed);
+                let file = file.clone();
+                tokio::spawn(async move {
+                    let _permit = permits.acquire_owned().await.expect("the semaphore is never closed");
+                    let result = pipeline.ingest_file(&file).await;
+                    let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                    match &result {
+                        Ok(_) => log::info!("[{}/{}] Finished {}", done, file_count, file.path.display()),
+                        Err(e) => log::error!("[{}/{}] Failed to ingest {}: {}", done, file_count, file.path.display(), e),
+                    }
+                    result
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut results = Vec::with_capacity(files.len());
+        for task in tasks {
+            results.push(task.await?);
+        }
+        let pipeline = Arc::into_inner(pipeline).expect("every task has finished");
+        pipeline.finish().await?;
+        Ok(results)
+    }
+
+    /// Extract, chunk, embed and store a single file, replacing any points previously stored for its source.
+    ///
+    /// Files that every sink stores with the same fingerprint, or that the journal records, are skipped.
+    pub async fn ingest_file(&self, file: &InputFile) -> anyhow::Result<FileOutcome> {
+        let path = file.path.as_path();
+        let bytes = std::fs::read(path)?;
+        let (file_hash, fingerprint) = self.processor.fingerprint(&bytes, &file.source);
+
+        if self.journal.is_file_done(&fingerprint) || self.is_stored(&fingerprint).await? {
+            log::info!("Skipping {}, already ingested with the same contents and settings", path.display());
+            return Ok(FileOutcome::Unchanged);
+        }
+
+        let document = self.processor.extract(path, &bytes)?;
+        drop(bytes);
+        let chunks = self.processor.chunks(path, &document);
+
+        for sink in &self.sinks {
+            sink.remove_other_versions(&file.source, &fingerprint).await?;
+        }
+
+        let markdown = self.processor.chunker.is_markdown(&document);
+        let mut payloads = Payloads::new(file, &file_hash, &fingerprint, &document, &chunks, markdown);
+
+        log::info!("Embedding and uploading chunks of {}...", path.display());
+        let batch_size = self.processor.embedder.batch_size();
+        let mut truncated = 0;
+        let mut count = 0;
+        let mut pending = Vec::new();
+        for (batch_number, batch) in chunks.chunks(batch_size).enumerate() {
+            if self.journal.is_batch_done(&fingerprint, batch_size, batch_number) {
+                count += batch.len();
+                continue;
+            }
+            let points = self
+                .processor
+                .embed_batch(batch_number * batch_size, batch, &mut payloads, &mut truncated)
+                .await?;
+            let (done, uploaded) = oneshot::channel();
+            let upload = Upload {
+                points,
+                fingerprint: fingerprint.clone(),
+                batch_size,
+                batch_number,
+                done,
+            };
+            self.uploads
+                .send(upload)
+                .await
+                .map_err(|_| anyhow::anyhow!("The upload task stopped"))?;
+            pending.push(uploaded);
+        }
+
+        for uploaded in pending {
+            count += uploaded.await.map_err(|_| anyhow::anyhow!("The upload task stopped"))??;
+        }
+
+        if truncated > 0 {
+            log::warn!(
+                "{} chunks of {} are longer than the {} tokens {} reads and were truncated; use --tokenizer model or a smaller --chunk-size",
+                truncated,
+                path.display(),
+                self.processor.embedder.max_tokens().unwrap_or_default(),
+                self.processor.embedder.model_name()
+            );
+        }
+        self.journal.file_done(&fingerprint, path, count)?;
+        log::info!("Uploaded {} embeddings of {}", count, path.display());
+
+        Ok(FileOutcome::Uploaded(count))
+    }
+
+    /// Wait for the queued batches to be written, then finish the sinks.
+    pub async fn finish(self) -> anyhow::Result<()> {
+        let IngestPipeline { uploads, uploader, .. } = self;
+        // The uploader stops once the last sender is dropped
+        drop(uploads);
+        uploader.await?
+    }
+
+    /// Whether every sink stores the file already.
+    async fn is_stored(&self, fingerprint: &str) -> anyhow::Result<bool> {
+        if self.sinks.is_empty() {
+            return Ok(false);
+        }
+        for sink in &self.sinks {
+            if !sink.is_stored(fingerprint).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A batch of points to store, and where to report the outcome.
+struct Upload {
+    points: Vec<PointStruct>,
+    /// Fingerprint of the file the batch belongs to
+    fingerprint: String,
+    batch_size: usize,
+    batch_number: usize,
+    done: oneshot::Sender<anyhow::Result<usize>>,
+}
+
+/// Write batches of points to every sink as they arrive, recording each in the journal and reporting the number of
+/// points stored to the file they belong to. The sinks are finished once the last batch is written.
+async fn upload(sinks: Vec<Arc<dyn Sink>>, journal: Arc<Journal>, mut batches: mpsc::Receiver<Upload>) -> anyhow::Result<()> {
+    while let Some(Upload { points, fingerprint, batch_size, batch_number, done }) = batches.recv().await {
+        let result = write(&sinks, &points)
+            .await
+            .and_then(|()| journal.batch_done(&fingerprint, batch_size, batch_number))
+            .map(|()| points.len());
+        // The file may already have failed and stopped waiting
+        let _ = done.send(result);
+    }
+    for sink in &sinks {
+        sink.finish().await?;
+    }
+    Ok(())
+}
+
+async fn write(sinks: &[Arc<dyn Sink>], points: &[PointStruct]) -> anyhow::Result<()> {
+    for sink in sinks {
+        sink.write(points).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_names_have_slashes_and_no_dot_components() {
+        assert_eq!(source_name(Path::new("./docs/2023/report.pdf")), "docs/2023/report.pdf");
+        assert_eq!(source_name(Path::new("/srv/docs/report.pdf")), "/srv/docs/report.pdf");
+        assert_eq!(source_name(Path::new("report.pdf")), "report.pdf");
+    }
+
+    #[test]
+    fn files_with_the_same_name_have_different_sources() {
+        let a = InputFile::from(PathBuf::from("2023/report.pdf"));
+        let b = InputFile::from(PathBuf::from("2024/report.pdf"));
+        assert_ne!(a.source, b.source);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::{Vector, Vectors};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::document::{CharCounter, Document};
+use crate::{embedding, runtime};
+use crate::markdown::Outline;
+use crate::pipeline::{Chunker, Embedder, Extractor, InputFile};
+use crate::sparse::{SparseEncoder, SparseKind, DENSE_VECTOR, SPARSE_VECTOR};
+
+/// Namespace for the UUIDv5 point IDs, so IDs don't collide with other v5 UUIDs in the collection
+const POINT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_9d3b_4c7a_8e25_b1f0_d8a3_5c91);
+
+/// The stages and settings that turn files into points, without storing them anywhere.
+pub struct Processor {
+    pub extractor: Box<dyn Extractor>,
+    pub chunker: Box<dyn Chunker>,
+    pub embedder: Box<dyn Embedder>,
+    pub sparse: Option<(SparseKind, SparseEncoder)>,
+    pub chunking: serde_json::Value,
+    pub metadata: Vec<(String, serde_json::Value)>,
+    /// Files are re-ingested whenever these settings change
+    pub settings: String,
+}
+
+impl Processor {
+    /// SHA-256 of the file contents, and the fingerprint combining it with the settings and the file's source.
+    ///
+    /// Copies of a file under different sources get different fingerprints, so each is stored and replaced on its own.
+    pub fn fingerprint(&self, bytes: &[u8], source: &str) -> (String, String) {
+        let file_hash = format!("{:x}", Sha256::digest(bytes));
+        let fingerprint = format!("{:x}", Sha256::digest(format!("{}:{}:{}", file_hash, self.settings, source)));
+        (file_hash, fingerprint)
+    }
+
+    /// Extract the file's text, page by page for PDFs.
+    pub fn extract(&self, path: &Path, bytes: &[u8]) -> anyhow::Result<Document> {
+        let document = runtime::blocking(|| self.extractor.extract(path, bytes))?;
+
+        if document.markdown {
+            log::info!("Read Markdown file: {}", path.display());
+        } else {
+            log::info!("Extracted text from {} pages of PDF file: {}", document.page_count(), path.display());
+        }
+        log::debug!("Extracted text of {}:\n{}", path.display(), document.text);
+        Ok(document)
+    }
+
+    /// Split the text into chunks, keeping each chunk's byte offset so it can be mapped back to its pages.
+    pub fn chunks<'a>(&self, path: &Path, document: &'a Document) -> Vec<(usize, &'a str)> {
+        let chunks = runtime::blocking(|| self.chunker.chunks(document));
+        log::info!("Created {} chunks from {}", chunks.len(), path.display());
+        log::debug!("Chunks of {}:\n{:?}", path.display(), chunks);
+        chunks
+    }
+
+    /// The payload of a chunk, with the settings it was processed with and any extra metadata.
+    pub fn payload(&self, payloads: &mut Payloads, chunk_number: usize, offset: usize, text: &str) -> serde_json::Value {
+        let mut payload = payloads.payload(chunk_number, offset, text);
+        payload["chunking"] = self.chunking.clone();
+        payload["embedding_backend"] = self.embedder.backend().into();
+        payload["embedding_model"] = self.embedder.model_name().into();
+        if let Some((sparse, _)) = &self.sparse {
+            payload["sparse_model"] = sparse.name().into();
+        }
+        // Extra metadata never overrides the fields above
+        for (key, value) in &self.metadata {
+            payload.as_object_mut().unwrap().entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        payload
+    }
+
+    /// Embed a batch of chunks, the first of which has the number `first`, and build their points.
+    pub async fn embed_batch(
+        &self,
+        first: usize,
+        batch: &[(usize, &str)],
+        payloads: &mut Payloads<'_>,
+        truncated: &mut usize,
+    ) -> anyhow::Result<Vec<PointStruct>> {
+        let texts = batch.iter().map(|(_, chunk)| *chunk).collect::<Vec<_>>();
+        if let (Some(tokenizer), Some(max_tokens)) = (self.embedder.tokenizer(), self.embedder.max_tokens()) {
+            *truncated += embedding::count_truncated(tokenizer, max_tokens, &texts)?;
+        }
+
+        let sparse_vectors = match &self.sparse {
+            Some((_, encoder)) => Some(runtime::blocking(|| encoder.embed_documents(&texts))?),
+            None => None,
+        };
+        let embeddings = self.embedder.embed(&texts).await?;
+        log::debug!("Embeddings:\n{:?}", embeddings);
+        if let Some(sparse_vectors) = &sparse_vectors {
+            log::debug!("Sparse vectors:\n{:?}", sparse_vectors);
+        }
+
+        let mut sparse_vectors = sparse_vectors.map(Vec::into_iter);
+        let points = embeddings
+            .into_iter()
+            .zip(batch)
+            .enumerate()
+            .map(|(i, (embedding, &(offset, text)))| {
+                let chunk_number = first + i;
+                let payload = self.payload(payloads, chunk_number, offset, text).to_string();
+                let vectors: Vectors = match sparse_vectors.as_mut().and_then(Iterator::next) {
+                    Some(sparse_vector) => HashMap::from([
+                        (DENSE_VECTOR.to_string(), Vector::from(embedding)),
+                        (SPARSE_VECTOR.to_string(), Vector::from(sparse_vector)),
+                    ])
+                    .into(),
+                    None => embedding.into(),
+                };
+                let id = point_id(payloads.source, payloads.file_hash, chunk_number).to_string();
+                PointStruct::new(id, vectors, serde_json::from_str(&payload).unwrap())
+            })
+            .collect();
+        Ok(points)
+    }
+}
+
+/// Builds the payloads of a file's chunks, in order.
+///
+/// Payloads have the structure:
+/// {file_name: <file_name>, source: <source>, text: <text>, chunk_number: <chunk_number>, chunk_count: <count>, page_start: <page>, page_end: <page>,
+///  char_start: <offset>, char_end: <offset>, chunking: <settings>, embedding_backend: <backend>, embedding_model: <model>, fingerprint: <fingerprint>}
+/// where source identifies the file among all ingested files, chunk_number is the index of the chunk in the file,
+/// pages are 1-based and character offsets index into the text of the whole document. Markdown chunks also get a
+/// heading_path like "Intro > Setup", and chunks with sparse vectors a sparse_model like "bm25".
+pub struct Payloads<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    file_hash: &'a str,
+    fingerprint: &'a str,
+    chunk_count: usize,
+    document: &'a Document,
+    outline: Option<Outline>,
+    starts: CharCounter<'a>,
+    ends: CharCounter<'a>,
+}
+
+impl<'a> Payloads<'a> {
+    pub fn new(
+        file: &'a InputFile,
+        file_hash: &'a str,
+        fingerprint: &'a str,
+        document: &'a Document,
+        chunks: &[(usize, &str)],
+        markdown: bool,
+    ) -> Self {
+        Payloads {
+            file_name: file.path.file_name().and_then(|name| name.to_str()).unwrap_or("unknown"),
+            source: &file.source,
+            file_hash,
+            fingerprint,
+            chunk_count: chunks.len(),
+            document,
+            outline: markdown.then(|| Outline::new(&document.text)),
+            starts: CharCounter::new(&document.text),
+            ends: CharCounter::new(&document.text),
+        }
+    }
+
+    /// The fields describing where the chunk comes from.
+    fn payload(&mut self, chunk_number: usize, offset: usize, text: &str) -> serde_json::Value {
+        let end = offset + text.len();
+        let mut payload = json!({
+            "file_name": self.file_name,
+            "source": self.source,
+            "text": text,
+            "chunk_number": chunk_number,
+            "chunk_count": self.chunk_count,
+            "page_start": self.document.page_at(offset),
+            "page_end": self.document.page_at(end.saturating_sub(1).max(offset)),
+            "char_start": self.starts.char_offset(offset),
+            "char_end": self.ends.char_offset(end),
+            "fingerprint": self.fingerprint
+        });
+        if let Some(heading_path) = self.outline.as_ref().and_then(|outline| outline.heading_path(offset)) {
+            payload["heading_path"] = heading_path.into();
+        }
+        payload
+    }
+}
+
+/// Derive a stable point ID from the file's source and contents and the chunk's position, so that uploading
+/// the same file again overwrites its points instead of duplicating them, while copies keep their own points.
+fn point_id(source: &str, file_hash: &str, chunk_number: usize) -> Uuid {
+    Uuid::new_v5(&POINT_ID_NAMESPACE, format!("{}:{}:{}", source, file_hash, chunk_number).as_bytes())
+}
@@ -0,0 +1,26 @@
+use std::io::IsTerminal;
+
+use crate::cli::GlobalArgs;
+use crate::error::Failure;
+
+/// Ask a yes/no question on the terminal.
+///
+/// With `--yes` the default answer is taken without asking. Without a terminal on stdin the
+/// question fails instead of blocking, so the tool never hangs in a pipeline.
+pub fn confirm(global: &GlobalArgs, question: &str, default: bool) -> anyhow::Result<bool> {
+    if global.yes {
+        return Ok(default);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(Failure::NeedsConfirmation(question.to_string()).into());
+    }
+
+    eprintln!("{} {}", question, if default { "(Y/n)" } else { "(y/N)" });
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
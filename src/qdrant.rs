@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::points_selector::PointsSelectorOneOf;
+use qdrant_client::qdrant::vectors_config::Config;
+use qdrant_client::qdrant::with_payload_selector::SelectorOptions;
+use qdrant_client::qdrant::{
+    CollectionParams, Condition, CountPoints, FieldType, Filter, PayloadIncludeSelector, PointsSelector, ScrollPoints,
+    SparseVectorConfig, SparseVectorParams, VectorParams, VectorParamsMap, VectorsConfig, WithPayloadSelector,
+};
+
+use crate::pipeline::Sink;
+use crate::sink::append_jsonl;
+use crate::sparse::{DENSE_VECTOR, SPARSE_VECTOR};
+use crate::{payload, retry};
+
+/// Number of times a batch is tried by default
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Check whether a collection with the given name exists.
+pub async fn collection_exists(client: &QdrantClient, collection_name: &str) -> anyhow::Result<bool> {
+    let collections_list = client.list_collections().await?;
+    Ok(collections_list.collections.iter().any(|collection| collection.name == collection_name))
+}
+
+/// Create a collection for the embedding model's vectors.
+///
+/// With sparse vectors the dense vector is named [`DENSE_VECTOR`] and stored next to a sparse vector named [`SPARSE_VECTOR`];
+/// otherwise the collection has a single unnamed dense vector.
+pub async fn create_collection(client: &QdrantClient, collection_name: &str, params: VectorParams, sparse: bool) -> anyhow::Result<()> {
+    let (config, sparse_vectors_config) = if sparse {
+        let dense = VectorParamsMap {
+            map: HashMap::from([(DENSE_VECTOR.to_string(), params)]),
+        };
+        let sparse = SparseVectorConfig {
+            map: HashMap::from([(SPARSE_VECTOR.to_string(), SparseVectorParams::default())]),
+        };
+        (Config::ParamsMap(dense), Some(sparse))
+    } else {
+        (Config::Params(params), None)
+    };
+    client
+        .create_collection(&CreateCollection {
+            collection_name: collection_name.to_string(),
+            vectors_config: Some(VectorsConfig { config: Some(config) }),
+            sparse_vectors_config,
+            ..Default::default()
+        })
+        .await?;
+    Ok(())
+}
+
+async fn collection_params(client: &QdrantClient, collection_name: &str) -> anyhow::Result<CollectionParams> {
+    client
+        .collection_info(collection_name)
+        .await?
+        .result
+        .and_then(|info| info.config)
+        .and_then(|config| config.params)
+        .ok_or_else(|| anyhow::anyhow!("No configuration returned for collection {}", collection_name))
+}
+
+/// Whether the collection stores sparse vectors next to its dense vectors.
+pub async fn has_sparse_vectors(client: &QdrantClient, collection_name: &str) -> anyhow::Result<bool> {
+    let params = collection_params(client, collection_name).await?;
+    Ok(params
+        .sparse_vectors_config
+        .is_some_and(|config| config.map.contains_key(SPARSE_VECTOR)))
+}
+
+/// Make sure an existing collection stores the vectors produced by the embedding models: dense vectors of the
+/// model's size, and sparse vectors exactly when they are requested.
+pub async fn check_vectors(client: &QdrantClient, collection_name: &str, size: u64, sparse: bool) -> anyhow::Result<()> {
+    let params = collection_params(client, collection_name).await?;
+    let existing = match params.vectors_config.and_then(|vectors_config| vectors_config.config) {
+        Some(Config::Params(params)) => Some(params.size),
+        Some(Config::ParamsMap(params)) => params.map.get(DENSE_VECTOR).map(|params| params.size),
+        None => None,
+    };
+    if let Some(existing) = existing {
+        if existing != size {
+            return Err(anyhow::anyhow!(
+                "Collection {} stores vectors of size {}, but the embedding model produces vectors of size {}",
+                collection_name,
+                existing,
+                size
+            ));
+        }
+    }
+
+    let has_sparse = params
+        .sparse_vectors_config
+        .is_some_and(|config| config.map.contains_key(SPARSE_VECTOR));
+    match (has_sparse, sparse) {
+        (true, false) => Err(anyhow::anyhow!("Collection {} stores sparse vectors, pass --sparse to compute them", collection_name)),
+        (false, true) => Err(anyhow::anyhow!("Collection {} has no sparse vectors, recreate it to add them", collection_name)),
+        _ => Ok(()),
+    }
+}
+
+/// Name of the embedding model recorded in the payload of the collection's points, if any.
+pub async fn stored_model(client: &QdrantClient, collection_name: &str) -> anyhow::Result<Option<String>> {
+    let stored = stored_field(client, collection_name, None, "embedding_model").await?;
+    Ok(stored.as_str().map(String::from))
+}
+
+/// Value of a payload field of the first point matching the filter, or `null` if there is none.
+pub async fn stored_field(
+    client: &QdrantClient,
+    collection_name: &str,
+    filter: Option<Filter>,
+    field: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let response = client
+        .scroll(&ScrollPoints {
+            collection_name: collection_name.to_string(),
+            filter,
+            limit: Some(1),
+            with_payload: Some(WithPayloadSelector {
+                selector_options: Some(SelectorOptions::Include(PayloadIncludeSelector {
+                    fields: vec![field.to_string()],
+                })),
+            }),
+            ..Default::default()
+        })
+        .await?;
+    Ok(response
+        .result
+        .into_iter()
+        .next()
+        .map(|point| payload::to_json(point.payload)[field].take())
+        .unwrap_or_default())
+}
+
+/// Name of the embedding backend recorded in the payload of the collection's points, if any.
+pub async fn stored_backend(client: &QdrantClient, collection_name: &str) -> anyhow::Result<Option<String>> {
+    let stored = stored_field(client, collection_name, None, "embedding_backend").await?;
+    Ok(stored.as_str().map(String::from))
+}
+
+/// Name of the sparse vector kind recorded in the payload of the collection's points, if any.
+pub async fn stored_sparse(client: &QdrantClient, collection_name: &str) -> anyhow::Result<Option<String>> {
+    let stored = stored_field(client, collection_name, None, "sparse_model").await?;
+    Ok(stored.as_str().map(String::from))
+}
+
+/// Index the payload fields used to look up already ingested files.
+pub async fn create_payload_indexes(client: &QdrantClient, collection_name: &str) -> anyhow::Result<()> {
+    for field in ["file_name", "source", "fingerprint"] {
+        client
+            .create_field_index(collection_name, field, FieldType::Keyword, None, None)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Count the points matching a filter.
+pub async fn count(client: &QdrantClient, collection_name: &str, filter: Filter) -> anyhow::Result<u64> {
+    let response = client
+        .count(&CountPoints {
+            collection_name: collection_name.to_string(),
+            filter: Some(filter),
+            exact: Some(true),
+            ..Default::default()
+        })
+        .await?;
+    Ok(response.result.map(|result| result.count).unwrap_or_default())
+}
+
+/// Delete the points matching a filter and wait for the deletion to be applied.
+pub async fn delete_matching(client: &QdrantClient, collection_name: &str, filter: Filter) -> anyhow::Result<()> {
+    let selector = PointsSelector {
+        points_selector_one_of: Some(PointsSelectorOneOf::Filter(filter)),
+    };
+    client
+        .delete_points_blocking(collection_name, None, &selector, None)
+        .await?;
+    Ok(())
+}
+
+/// Upsert points and wait for them to be applied, retrying failed attempts with jittered exponential backoff.
+pub async fn upsert_with_retry(
+    client: &QdrantClient,
+    collection_name: &str,
+    points: &[PointStruct],
+    max_attempts: u32,
+) -> anyhow::Result<()> {
+    let mut attempt = 1;
+    loop {
+        match client.upsert_points_blocking(collection_name, None, points.to_vec(), None).await {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= max_attempts => {
+                return Err(anyhow::anyhow!("Upload failed after {} attempt(s): {:#}", attempt, e));
+            }
+            Err(e) => {
+                let delay = retry::backoff(attempt);
+                log::warn!(
+                    "Upload attempt {} of {} failed: {:#}; retrying in {:.1}s",
+                    attempt,
+                    max_attempts,
+                    e,
+                    delay.as_secs_f32()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Stores points in an existing Qdrant collection.
+pub struct QdrantSink {
+    client: QdrantClient,
+    collection_name: String,
+    max_attempts: u32,
+    failed_points: Option<PathBuf>,
+}
+
+impl QdrantSink {
+    pub fn new(client: QdrantClient, collection_name: &str) -> Self {
+        QdrantSink {
+            client,
+            collection_name: collection_name.to_string(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            failed_points: None,
+        }
+    }
+
+    /// Number of times to try uploading a batch.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Append the points of batches that still fail after every attempt to this JSON Lines file.
+    pub fn failed_points(mut self, path: &Path) -> Self {
+        self.failed_points = Some(path.to_path_buf());
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for QdrantSink {
+    async fn write(&self, points: &[PointStruct]) -> anyhow::Result<()> {
+        let Err(e) = upsert_with_retry(&self.client, &self.collection_name, points, self.max_attempts).await else {
+            return Ok(());
+        };
+        let Some(failed_points) = &self.failed_points else {
+            return Err(e);
+        };
+        match append_jsonl(failed_points, points) {
+            Ok(()) => Err(anyhow::anyhow!(
+                "{:#}; saved its {} point(s) to {}, upload them with `replay`",
+                e,
+                points.len(),
+                failed_points.display()
+            )),
+            Err(dump_error) => Err(anyhow::anyhow!("{:#}; saving its points also failed: {}", e, dump_error)),
+        }
+    }
+
+    /// An interrupted upload leaves fewer points than the `chunk_count` recorded in their payload.
+    /// Points without a `chunk_count` can't be checked, so the file is uploaded again.
+    async fn is_stored(&self, fingerprint: &str) -> anyhow::Result<bool> {
+        let fingerprint_filter = Filter::must([Condition::matches("fingerprint", fingerprint.to_string())]);
+        let count = count(&self.client, &self.collection_name, fingerprint_filter.clone()).await?;
+        if count == 0 {
+            return Ok(false);
+        }
+        let chunk_count = stored_field(&self.client, &self.collection_name, Some(fingerprint_filter), "chunk_count").await?;
+        Ok(matches!(chunk_count.as_u64(), Some(chunk_count) if count >= chunk_count))
+    }
+
+    /// Remove the chunks of a previous version of the file, keeping those of an interrupted upload of this version.
+    ///
+    /// Points uploaded before the source was recorded only have a `file_name`, so those with the source's file name
+    /// are taken to be a previous version too.
+    async fn remove_other_versions(&self, source: &str, fingerprint: &str) -> anyhow::Result<()> {
+        let file_name = source.rsplit('/').next().unwrap_or(source);
+        let unsourced = Filter::must([
+            Condition::matches("file_name", file_name.to_string()),
+            Condition::is_empty("source"),
+        ]);
+        let file_filter = Filter {
+            should: vec![Condition::matches("source", source.to_string()), unsourced.into()],
+            must_not: vec![Condition::matches("fingerprint", fingerprint.to_string())],
+            ..Default::default()
+        };
+        if count(&self.client, &self.collection_name, file_filter.clone()).await? > 0 {
+            log::info!("Removing previously uploaded chunks of {}", source);
+            delete_matching(&self.client, &self.collection_name, file_filter).await?;
+        }
+        Ok(())
+    }
+}
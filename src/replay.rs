@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use vectordb::pipeline::BATCH_SIZE;
+use vectordb::qdrant;
+use vectordb::sink::{point_from_json, to_jsonl};
+
+use crate::cli::{GlobalArgs, ReplayArgs};
+use crate::connection;
+
+/// Directory the points that could not be uploaded are saved to
+const FAILED_DIR: &str = ".ingest_failed";
+
+/// Default file for the points of a collection that could not be uploaded.
+pub fn default_path(collection_name: &str) -> PathBuf {
+    Path::new(FAILED_DIR).join(format!("{}.jsonl", collection_name))
+}
+
+/// Upload the points saved by a failed ingest, keeping only those that fail again.
+pub async fn run(args: ReplayArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let path = args.file.unwrap_or_else(|| default_path(&args.collection));
+    let contents = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let points = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(anyhow::Error::from)
+                .and_then(point_from_json)
+                .map_err(|e| anyhow::anyhow!("Invalid point on line {} of {}: {}", number + 1, path.display(), e))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if points.is_empty() {
+        println!("No points to replay in {}", path.display());
+        return Ok(());
+    }
+    println!("Replaying {} point(s) from {}", points.len(), path.display());
+
+    let client = connection::connect(global).await?;
+    if !qdrant::collection_exists(&client, &args.collection).await? {
+        return Err(anyhow::anyhow!("Collection {} does not exist", args.collection));
+    }
+
+    let mut unsent = Vec::new();
+    for batch in points.chunks(BATCH_SIZE) {
+        if let Err(e) = qdrant::upsert_with_retry(&client, &args.collection, batch, args.max_attempts).await {
+            println!("{:#}", e);
+            unsent.extend_from_slice(batch);
+        }
+    }
+    println!("Uploaded {} point(s) to collection {}", points.len() - unsent.len(), args.collection);
+
+    // Only the points that failed again are kept for the next replay
+    if unsent.is_empty() {
+        std::fs::remove_file(&path)?;
+    } else {
+        std::fs::write(&path, to_jsonl(&unsent))?;
+        return Err(anyhow::anyhow!("{} point(s) could not be uploaded and remain in {}", unsent.len(), path.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Longest wait before the second attempt, doubled for every further attempt
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait between attempts
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// A random wait of up to `BASE_DELAY * 2^(attempt - 1)`, capped at `MAX_DELAY`, so that requests which
+/// failed together don't all retry at the same moment.
+pub fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_DELAY);
+    // Every RandomState is seeded with fresh random keys
+    let random = RandomState::new().build_hasher().finish();
+    ceiling.mul_f64(random as f64 / u64::MAX as f64)
+}
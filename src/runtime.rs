@@ -0,0 +1,33 @@
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Run blocking work, like extracting, chunking, embedding or writing a file, from async code.
+///
+/// On a multi-threaded runtime the work is CPU-bound for long enough that the runtime should move its other tasks to
+/// another thread meanwhile. `block_in_place` does that, but panics on a current-thread runtime, which has no other
+/// thread to move them to, so there the work simply runs in place.
+pub(crate) fn blocking<T>(work: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(work),
+        _ => work(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_on_a_current_thread_runtime() {
+        assert_eq!(blocking(|| 1 + 1), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_on_a_multi_thread_runtime() {
+        assert_eq!(blocking(|| 1 + 1), 2);
+    }
+
+    #[test]
+    fn runs_outside_a_runtime() {
+        assert_eq!(blocking(|| 1 + 1), 2);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::{ScoredPoint, SearchPoints, SparseIndices};
+
+use vectordb::sparse::{SparseEncoder, SparseKind, DENSE_VECTOR, SPARSE_VECTOR};
+use vectordb::embedding::Backend;
+use vectordb::{filter, payload, qdrant};
+
+use crate::cli::{GlobalArgs, SearchArgs};
+use crate::{backend, connection};
+
+/// Rank constant of reciprocal rank fusion, which keeps the top ranks of one list from dominating
+const RRF_K: f32 = 60.0;
+/// A hybrid search fetches this many times `--limit` candidates from each list before fusing them
+const HYBRID_CANDIDATES: u64 = 4;
+
+pub async fn run(args: SearchArgs, global: &GlobalArgs) -> anyhow::Result<()> {
+    let client = connection::connect(global).await?;
+    if !qdrant::collection_exists(&client, &args.collection).await? {
+        return Err(anyhow::anyhow!("Collection {} does not exist", args.collection));
+    }
+
+    let embedding_backend = match args.embedder.backend {
+        Some(backend) => backend,
+        None => detect_backend(&client, &args.collection).await?,
+    };
+    let model = match args.model {
+        Some(model) => Some(model),
+        None => qdrant::stored_model(&client, &args.collection).await?,
+    };
+    let embedder = backend::load(embedding_backend, &args.embedder, model, &global.model_cache()).await?;
+    if !args.json {
+        println!("Embedding model: {} ({})", embedder.model_name(), embedder.backend());
+    }
+
+    let vector = embedder
+        .embed(&[args.query.as_str()])
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow::anyhow!("Embedding model returned no vector for the query"))?;
+    if global.debug {
+        println!("Query embedding:");
+        println!("{:?}", vector);
+    }
+
+    let sparse_vectors = qdrant::has_sparse_vectors(&client, &args.collection).await?;
+    if args.hybrid && !sparse_vectors {
+        return Err(anyhow::anyhow!("Collection {} has no sparse vectors, ingest it with --sparse to use --hybrid", args.collection));
+    }
+
+    let filter = filter::build(&args.filter, &args.should, &args.must_not);
+    let limit = if args.hybrid { args.limit * HYBRID_CANDIDATES } else { args.limit };
+    let dense_search = SearchPoints {
+        collection_name: args.collection.clone(),
+        vector,
+        vector_name: sparse_vectors.then(|| DENSE_VECTOR.to_string()),
+        limit,
+        filter: filter.clone(),
+        score_threshold: args.score_threshold,
+        with_payload: Some(true.into()),
+        ..Default::default()
+    };
+    let mut hits = client.search_points(&dense_search).await?.result;
+
+    if args.hybrid {
+        let sparse = detect_sparse(&client, &args.collection).await?;
+        if !args.json {
+            println!("Sparse vectors: {}", sparse.name());
+        }
+        let (indices, values): (Vec<u32>, Vec<f32>) = SparseEncoder::load(sparse)?.embed_query(&args.query)?.into_iter().unzip();
+        let sparse_hits = if indices.is_empty() {
+            Vec::new()
+        } else {
+            let sparse_search = SearchPoints {
+                collection_name: args.collection.clone(),
+                vector: values,
+                sparse_indices: Some(SparseIndices { data: indices }),
+                vector_name: Some(SPARSE_VECTOR.to_string()),
+                limit,
+                filter,
+                with_payload: Some(true.into()),
+                ..Default::default()
+            };
+            client.search_points(&sparse_search).await?.result
+        };
+        hits = fuse(vec![hits, sparse_hits], args.limit as usize);
+    }
+
+    if args.json {
+        let hits = hits
+            .into_iter()
+            .map(|point| {
+                let mut hit = payload::to_json(point.payload);
+                hit["id"] = payload::point_id_to_json(point.id);
+                hit["score"] = point.score.into();
+                hit
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No results found");
+    }
+    for (rank, point) in hits.into_iter().enumerate() {
+        let payload = payload::to_json(point.payload);
+        let pages = match (payload["page_start"].as_u64(), payload["page_end"].as_u64()) {
+            (Some(start), Some(end)) if start == end => format!(" | page {}", start),
+            (Some(start), Some(end)) => format!(" | pages {}-{}", start, end),
+            _ => String::new(),
+        };
+        println!(
+            "{}. score {:.4} | {} #{}{}",
+            rank + 1,
+            point.score,
+            payload["file_name"].as_str().unwrap_or("unknown"),
+            payload["chunk_number"],
+            pages,
+        );
+        println!("{}", payload["text"].as_str().unwrap_or_default());
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Find the backend the collection was ingested with, falling back to fastembed for collections uploaded
+/// before the backend was stored in the payload.
+async fn detect_backend(client: &QdrantClient, collection_name: &str) -> anyhow::Result<Backend> {
+    match qdrant::stored_backend(client, collection_name).await? {
+        Some(name) => Backend::from_name(&name)
+            .ok_or_else(|| anyhow::anyhow!("Collection {} was ingested with unknown embedding backend {}", collection_name, name)),
+        None => Ok(Backend::Fastembed),
+    }
+}
+
+/// Find the kind of sparse vectors the collection was ingested with.
+async fn detect_sparse(client: &QdrantClient, collection_name: &str) -> anyhow::Result<SparseKind> {
+    let name = qdrant::stored_sparse(client, collection_name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Collection {} does not record how its sparse vectors were computed", collection_name))?;
+    SparseKind::from_name(&name)
+        .ok_or_else(|| anyhow::anyhow!("Collection {} stores unknown sparse vectors {}", collection_name, name))
+}
+
+/// Merge ranked result lists with reciprocal rank fusion: each point scores `1 / (RRF_K + rank)` summed over the
+/// lists it appears in, so points ranked well by both dense and sparse search come first.
+fn fuse(lists: Vec<Vec<ScoredPoint>>, limit: usize) -> Vec<ScoredPoint> {
+    let mut fused: Vec<ScoredPoint> = Vec::new();
+    let mut positions = HashMap::<String, usize>::new();
+    for list in lists {
+        for (rank, mut point) in list.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            let id = payload::point_id_to_json(point.id.clone()).to_string();
+            match positions.get(&id) {
+                Some(&position) => fused[position].score += score,
+                None => {
+                    positions.insert(id, fused.len());
+                    point.score = score;
+                    fused.push(point);
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused.truncate(limit);
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(ids: &[u64]) -> Vec<ScoredPoint> {
+        ids.iter()
+            .map(|&id| ScoredPoint {
+                id: Some(id.into()),
+                // The scores of the lists are on different scales, fusion only looks at ranks
+                score: 100.0 - id as f32,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn ids(points: &[ScoredPoint]) -> Vec<String> {
+        points.iter().map(|point| payload::point_id_to_json(point.id.clone()).to_string()).collect()
+    }
+
+    #[test]
+    fn points_in_both_lists_sum_their_reciprocal_ranks() {
+        let fused = fuse(vec![points(&[1, 2]), points(&[2, 3])], 10);
+        assert_eq!(ids(&fused), vec!["2", "1", "3"]);
+        let rrf = |rank: f32| 1.0 / (RRF_K + rank);
+        assert_eq!(fused[0].score, rrf(2.0) + rrf(1.0));
+        assert_eq!(fused[1].score, rrf(1.0));
+        assert_eq!(fused[2].score, rrf(2.0));
+    }
+
+    #[test]
+    fn points_are_listed_once() {
+        let fused = fuse(vec![points(&[1, 2, 3]), points(&[3, 2, 1])], 10);
+        // Ranks 1 and 3 beat 2 and 2, and the tie of 1 and 3 keeps the order of the first list
+        assert_eq!(ids(&fused), vec!["1", "3", "2"]);
+    }
+
+    #[test]
+    fn results_are_cut_to_the_limit() {
+        let fused = fuse(vec![points(&[1, 2, 3, 4]), points(&[5, 6, 4])], 2);
+        assert_eq!(ids(&fused), vec!["4", "1"]);
+    }
+
+    #[test]
+    fn a_single_list_keeps_its_order() {
+        assert_eq!(ids(&fuse(vec![points(&[3, 1, 2])], 10)), vec!["3", "1", "2"]);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{Array, AsArray, ListBuilder, PrimitiveBuilder, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Float32Type, Schema, SchemaRef, UInt32Type};
+use async_trait::async_trait;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ArrowWriter;
+use qdrant_client::prelude::*;
+use qdrant_client::qdrant::vectors::VectorsOptions;
+use qdrant_client::qdrant::{PointId, Vector, Vectors};
+use serde_json::json;
+
+use crate::{payload, runtime};
+use crate::pipeline::Sink;
+use crate::sparse::{DENSE_VECTOR, SPARSE_VECTOR};
+
+/// File format of exported points, chosen by the file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line:
+    /// `{"id": ..., "vector": [...] or {"dense": [...], "sparse": {"indices": [...], "values": [...]}}, "payload": {...}}`
+    Jsonl,
+    /// Columns `id`, `vector`, `sparse_indices`, `sparse_values` and `payload`, the latter as a JSON string
+    Parquet,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl" | "ndjson" | "json") => Ok(Format::Jsonl),
+            Some("parquet" | "pq") => Ok(Format::Parquet),
+            _ => Err(anyhow::anyhow!("Cannot tell the format of {}, use a .jsonl or .parquet extension", path.display())),
+        }
+    }
+}
+
+/// Writes points to a file on disk.
+pub struct FileSink {
+    /// Taken when the file is finished
+    writer: Mutex<Option<Writer>>,
+}
+
+enum Writer {
+    Jsonl(BufWriter<File>),
+    Parquet(Box<ArrowWriter<File>>),
+}
+
+impl FileSink {
+    /// Create the file, replacing any existing one.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let format = Format::from_path(path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))?;
+        let writer = match format {
+            Format::Jsonl => Writer::Jsonl(BufWriter::new(file)),
+            Format::Parquet => Writer::Parquet(Box::new(ArrowWriter::try_new(file, parquet_schema(), None)?)),
+        };
+        Ok(FileSink { writer: Mutex::new(Some(writer)) })
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink {
+    async fn write(&self, points: &[PointStruct]) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        let writer = writer.as_mut().ok_or_else(|| anyhow::anyhow!("The output file is already finished"))?;
+        runtime::blocking(|| -> anyhow::Result<()> {
+            match writer {
+                Writer::Jsonl(writer) => {
+                    for point in points {
+                        writeln!(writer, "{}", point_to_json(point))?;
+                    }
+                }
+                Writer::Parquet(writer) => writer.write(&to_record_batch(points)?)?,
+            }
+            Ok(())
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to write to the output file: {}", e))
+    }
+
+    /// Flush the remaining points and, for Parquet, write the file footer.
+    async fn finish(&self) -> anyhow::Result<()> {
+        match self.writer.lock().unwrap().take() {
+            Some(Writer::Jsonl(mut writer)) => writer.flush()?,
+            Some(Writer::Parquet(writer)) => {
+                writer.close()?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Reads the points of a file written by a [`FileSink`], a batch at a time.
+pub enum PointReader {
+    Jsonl { lines: Lines<BufReader<File>>, line_number: usize, batch_size: usize },
+    Parquet(ParquetRecordBatchReader),
+}
+
+impl PointReader {
+    pub fn open(path: &Path, batch_size: usize) -> anyhow::Result<Self> {
+        let format = Format::from_path(path)?;
+        let file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+        Ok(match format {
+            Format::Jsonl => PointReader::Jsonl {
+                lines: BufReader::new(file).lines(),
+                line_number: 0,
+                batch_size,
+            },
+            Format::Parquet => {
+                let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+                if builder.schema().fields() != parquet_schema().fields() {
+                    return Err(anyhow::anyhow!("{} does not contain exported points", path.display()));
+                }
+                PointReader::Parquet(builder.with_batch_size(batch_size).build()?)
+            }
+        })
+    }
+
+    /// The next batch of points, or `None` at the end of the file.
+    pub fn next_batch(&mut self) -> anyhow::Result<Option<Vec<PointStruct>>> {
+        match self {
+            PointReader::Jsonl { lines, line_number, batch_size } => {
+                let mut points = Vec::with_capacity(*batch_size);
+                while points.len() < *batch_size {
+                    let Some(line) = lines.next() else {
+                        break;
+                    };
+                    *line_number += 1;
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let point = serde_json::from_str(&line)
+                        .map_err(anyhow::Error::from)
+                        .and_then(point_from_json)
+                        .map_err(|e| anyhow::anyhow!("Invalid point on line {}: {}", line_number, e))?;
+                    points.push(point);
+                }
+                Ok((!points.is_empty()).then_some(points))
+            }
+            PointReader::Parquet(reader) => match reader.next() {
+                Some(batch) => Ok(Some(from_record_batch(&batch?)?)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+/// Append points to a JSON Lines file.
+pub fn append_jsonl(path: &Path, points: &[PointStruct]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(to_jsonl(points).as_bytes())?;
+    Ok(())
+}
+
+/// The points as JSON Lines.
+pub fn to_jsonl(points: &[PointStruct]) -> String {
+    let mut lines = String::new();
+    for point in points {
+        lines.push_str(&point_to_json(point).to_string());
+        lines.push('\n');
+    }
+    lines
+}
+
+pub fn point_to_json(point: &PointStruct) -> serde_json::Value {
+    let vector = match point.vectors.as_ref().and_then(|vectors| vectors.vectors_options.as_ref()) {
+        Some(VectorsOptions::Vector(vector)) => vector_to_json(vector),
+        Some(VectorsOptions::Vectors(named)) => named
+            .vectors
+            .iter()
+            .map(|(name, vector)| (name.clone(), vector_to_json(vector)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        None => serde_json::Value::Null,
+    };
+    json!({
+        "id": payload::point_id_to_json(point.id.clone()),
+        "vector": vector,
+        "payload": payload::to_json(point.payload.clone()),
+    })
+}
+
+fn vector_to_json(vector: &Vector) -> serde_json::Value {
+    match &vector.indices {
+        Some(indices) => json!({ "indices": indices.data, "values": vector.data }),
+        None => json!(vector.data),
+    }
+}
+
+pub fn point_from_json(point: serde_json::Value) -> anyhow::Result<PointStruct> {
+    let id = point_id_from_json(&point["id"])?;
+    let vectors: Vectors = match &point["vector"] {
+        serde_json::Value::Object(named) => named
+            .iter()
+            .map(|(name, vector)| Ok((name.clone(), vector_from_json(vector)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?
+            .into(),
+        vector => serde_json::from_value::<Vec<f32>>(vector.clone())?.into(),
+    };
+    let payload = point["payload"].to_string();
+    Ok(PointStruct::new(id, vectors, serde_json::from_str(&payload)?))
+}
+
+fn point_id_from_json(id: &serde_json::Value) -> anyhow::Result<PointId> {
+    match id {
+        serde_json::Value::String(uuid) => Ok(uuid.clone().into()),
+        serde_json::Value::Number(num) if num.is_u64() => Ok(num.as_u64().unwrap().into()),
+        other => Err(anyhow::anyhow!("invalid point ID {}", other)),
+    }
+}
+
+fn vector_from_json(vector: &serde_json::Value) -> anyhow::Result<Vector> {
+    if vector.is_object() {
+        let indices = serde_json::from_value::<Vec<u32>>(vector["indices"].clone())?;
+        let values = serde_json::from_value::<Vec<f32>>(vector["values"].clone())?;
+        Ok(Vector::from(indices.into_iter().zip(values).collect::<Vec<_>>()))
+    } else {
+        Ok(Vector::from(serde_json::from_value::<Vec<f32>>(vector.clone())?))
+    }
+}
+
+/// Parquet columns of a point. The sparse columns are null for points without a sparse vector, and the dense
+/// vector of a point with one is stored under the name `dense`.
+fn parquet_schema() -> SchemaRef {
+    let list = |item: DataType| DataType::List(Arc::new(Field::new("item", item, true)));
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("vector", list(DataType::Float32), false),
+        Field::new("sparse_indices", list(DataType::UInt32), true),
+        Field::new("sparse_values", list(DataType::Float32), true),
+        Field::new("payload", DataType::Utf8, false),
+    ]))
+}
+
+fn to_record_batch(points: &[PointStruct]) -> anyhow::Result<RecordBatch> {
+    let mut ids = Vec::with_capacity(points.len());
+    let mut dense = ListBuilder::new(PrimitiveBuilder::<Float32Type>::new());
+    let mut sparse_indices = ListBuilder::new(PrimitiveBuilder::<UInt32Type>::new());
+    let mut sparse_values = ListBuilder::new(PrimitiveBuilder::<Float32Type>::new());
+    let mut payloads = Vec::with_capacity(points.len());
+
+    for point in points {
+        ids.push(match payload::point_id_to_json(point.id.clone()) {
+            serde_json::Value::String(uuid) => uuid,
+            num => num.to_string(),
+        });
+        let (vector, sparse) = match point.vectors.as_ref().and_then(|vectors| vectors.vectors_options.as_ref()) {
+            Some(VectorsOptions::Vector(vector)) => (Some(vector), None),
+            Some(VectorsOptions::Vectors(named)) => (named.vectors.get(DENSE_VECTOR), named.vectors.get(SPARSE_VECTOR)),
+            None => (None, None),
+        };
+        let vector = vector.ok_or_else(|| anyhow::anyhow!("Point has no dense vector"))?;
+        dense.values().append_slice(&vector.data);
+        dense.append(true);
+        match sparse.and_then(|sparse| sparse.indices.as_ref().map(|indices| (indices, &sparse.data))) {
+            Some((indices, values)) => {
+                sparse_indices.values().append_slice(&indices.data);
+                sparse_indices.append(true);
+                sparse_values.values().append_slice(values);
+                sparse_values.append(true);
+            }
+            None => {
+                sparse_indices.append(false);
+                sparse_values.append(false);
+            }
+        }
+        payloads.push(payload::to_json(point.payload.clone()).to_string());
+    }
+
+    Ok(RecordBatch::try_new(
+        parquet_schema(),
+        vec![
+            Arc::new(StringArray::from(ids)),
+            Arc::new(dense.finish()),
+            Arc::new(sparse_indices.finish()),
+            Arc::new(sparse_values.finish()),
+            Arc::new(StringArray::from(payloads)),
+        ],
+    )?)
+}
+
+fn from_record_batch(batch: &RecordBatch) -> anyhow::Result<Vec<PointStruct>> {
+    let ids = batch.column(0).as_string::<i32>();
+    let dense = batch.column(1).as_list::<i32>();
+    let sparse_indices = batch.column(2).as_list::<i32>();
+    let sparse_values = batch.column(3).as_list::<i32>();
+    let payloads = batch.column(4).as_string::<i32>();
+
+    (0..batch.num_rows())
+        .map(|row| {
+            let id = match ids.value(row).parse::<u64>() {
+                Ok(num) => PointId::from(num),
+                Err(_) => PointId::from(ids.value(row).to_string()),
+            };
+            let vector = dense.value(row).as_primitive::<Float32Type>().values().to_vec();
+            let vectors: Vectors = if sparse_indices.is_null(row) {
+                vector.into()
+            } else {
+                let indices = sparse_indices.value(row).as_primitive::<UInt32Type>().values().to_vec();
+                let values = sparse_values.value(row).as_primitive::<Float32Type>().values().to_vec();
+                HashMap::from([
+                    (DENSE_VECTOR.to_string(), Vector::from(vector)),
+                    (SPARSE_VECTOR.to_string(), Vector::from(indices.into_iter().zip(values).collect::<Vec<_>>())),
+                ])
+                .into()
+            };
+            Ok(PointStruct::new(id, vectors, serde_json::from_str(payloads.value(row))?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn points() -> Vec<PointStruct> {
+        let payload = |text: &str| serde_json::from_value(json!({"text": text, "chunk_number": 0})).unwrap();
+        let sparse = HashMap::from([
+            (DENSE_VECTOR.to_string(), Vector::from(vec![0.5, -1.25])),
+            (SPARSE_VECTOR.to_string(), Vector::from(vec![(3, 0.75), (17, 2.0)])),
+        ]);
+        vec![
+            PointStruct::new("8c3b5f4e-2f7a-5c1d-9e0b-6a4d2c1f0e9b".to_string(), vec![0.25, 1.0], payload("dense")),
+            PointStruct::new(42, sparse, payload("sparse")),
+        ]
+    }
+
+    /// A fresh directory for the test's files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vectordb-sink-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn round_trip(file_name: &str) -> Vec<PointStruct> {
+        let dir = temp_dir(file_name);
+        let path = dir.join(file_name);
+        let sink = FileSink::create(&path).unwrap();
+        sink.write(&points()).await.unwrap();
+        sink.finish().await.unwrap();
+
+        let mut reader = PointReader::open(&path, 1).unwrap();
+        let mut read = Vec::new();
+        while let Some(batch) = reader.next_batch().unwrap() {
+            assert_eq!(batch.len(), 1);
+            read.extend(batch);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+        read
+    }
+
+    #[tokio::test]
+    async fn jsonl_round_trip() {
+        assert_eq!(round_trip("points.jsonl").await, points());
+    }
+
+    #[tokio::test]
+    async fn parquet_round_trip() {
+        assert_eq!(round_trip("points.parquet").await, points());
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::from_path(Path::new("out.ndjson")).unwrap(), Format::Jsonl);
+        assert_eq!(Format::from_path(Path::new("out.pq")).unwrap(), Format::Parquet);
+        assert!(Format::from_path(Path::new("out.csv")).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Name of the dense vector in collections that also store sparse vectors
+pub const DENSE_VECTOR: &str = "dense";
+/// Name of the sparse vector
+pub const SPARSE_VECTOR: &str = "sparse";
+
+/// BM25 term frequency saturation
+const BM25_K1: f32 = 1.2;
+/// BM25 length normalization
+const BM25_B: f32 = 0.75;
+/// Chunk length, in words, that BM25 weights are normalized against
+const BM25_AVERAGE_LENGTH: f32 = 150.0;
+
+/// How sparse vectors are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SparseKind {
+    /// BM25 term weights computed locally
+    Bm25,
+}
+
+impl SparseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SparseKind::Bm25 => "bm25",
+        }
+    }
+
+    /// Parse the name recorded in the payload.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [SparseKind::Bm25].into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// A sparse vector as `(index, value)` pairs.
+pub type SparseVector = Vec<(u32, f32)>;
+
+/// Computes sparse vectors for chunks and queries.
+pub enum SparseEncoder {
+    Bm25,
+}
+
+impl SparseEncoder {
+    pub fn load(kind: SparseKind) -> anyhow::Result<Self> {
+        Ok(match kind {
+            SparseKind::Bm25 => SparseEncoder::Bm25,
+        })
+    }
+
+    pub fn embed_documents(&self, texts: &[&str]) -> anyhow::Result<Vec<SparseVector>> {
+        match self {
+            SparseEncoder::Bm25 => Ok(texts.iter().map(|text| bm25_document(text)).collect()),
+        }
+    }
+
+    pub fn embed_query(&self, query: &str) -> anyhow::Result<SparseVector> {
+        match self {
+            SparseEncoder::Bm25 => Ok(bm25_query(query)),
+        }
+    }
+}
+
+/// Lowercased words of the text.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.unicode_words().map(str::to_lowercase)
+}
+
+/// Index of a term in the sparse vector: its 32-bit FNV-1a hash, so no vocabulary has to be stored.
+fn term_index(term: &str) -> u32 {
+    term.bytes().fold(0x811c_9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+/// The BM25 weight of every term in a chunk.
+///
+/// The inverse document frequency is left out, as it depends on the whole collection, so rare and
+/// common terms count the same.
+fn bm25_document(text: &str) -> SparseVector {
+    let mut frequencies = HashMap::new();
+    let mut length = 0;
+    for term in terms(text) {
+        *frequencies.entry(term_index(&term)).or_insert(0.0) += 1.0;
+        length += 1;
+    }
+    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length as f32 / BM25_AVERAGE_LENGTH);
+    frequencies
+        .into_iter()
+        .map(|(index, frequency)| (index, frequency * (BM25_K1 + 1.0) / (frequency + norm)))
+        .collect()
+}
+
+/// A query matches every one of its terms once, so a chunk's score is the sum of its weights for those terms.
+fn bm25_query(query: &str) -> SparseVector {
+    let mut indices = terms(query).map(|term| term_index(&term)).collect::<Vec<_>>();
+    indices.sort_unstable();
+    indices.dedup();
+    indices.into_iter().map(|index| (index, 1.0)).collect()
+}
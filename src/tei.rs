@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::api::{self, ApiConfig};
+use crate::embedding::{self, Backend};
+use crate::pipeline::Embedder;
+
+/// Default address of a local Text Embeddings Inference server
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+
+/// Embeds texts with the `/embed` endpoint of a Hugging Face Text Embeddings Inference server.
+///
+/// The server runs a single model, which is named by its `/info` endpoint.
+pub struct TeiEmbedder {
+    client: reqwest::Client,
+    url: Url,
+    model: String,
+    config: ApiConfig,
+    dimensions: u64,
+}
+
+impl TeiEmbedder {
+    /// Ask the server which model it runs and how many inputs it accepts per request, then embed a short text
+    /// to learn the size of the vectors.
+    ///
+    /// Fails when a model is given and the server runs a different one.
+    pub async fn connect(mut config: ApiConfig) -> anyhow::Result<Self> {
+        let client = api::client()?;
+        let mut info = client.get(config.endpoint(&["info"])?);
+        if let Some(api_key) = &config.api_key {
+            info = info.bearer_auth(api_key);
+        }
+        let info = api::send(info, config.max_attempts).await?;
+        let model = info["model_id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("{} did not name its model", config.base_url))?
+            .to_string();
+        if let Some(requested) = &config.model {
+            if *requested != model {
+                return Err(anyhow::anyhow!("{} serves model {}, not {}", config.base_url, model, requested));
+            }
+        }
+        if let Some(max_batch) = info["max_client_batch_size"].as_u64() {
+            config.batch_size = config.batch_size.min(max_batch as usize);
+        }
+
+        let mut embedder = TeiEmbedder {
+            client,
+            url: config.endpoint(&["embed"])?,
+            model,
+            config,
+            dimensions: 0,
+        };
+        let vectors = embedder.request(embedding::PROBE).await?;
+        embedder.dimensions = embedding::probed_dimensions(&embedder.url, vectors)?;
+        Ok(embedder)
+    }
+
+    async fn request(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        // Longer inputs are cut to the model's maximum length, like fastembed does, rather than rejected
+        let body = json!({
+            "inputs": texts,
+            "truncate": true,
+        });
+        let mut request = self.client.post(self.url.clone()).json(&body);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        api::parse_vectors(&api::send(request, self.config.max_attempts).await?, texts.len())
+    }
+}
+
+#[async_trait]
+impl Embedder for TeiEmbedder {
+    fn backend(&self) -> String {
+        Backend::Tei.name().to_string()
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn dimensions(&self) -> u64 {
+        self.dimensions
+    }
+
+    fn batch_size(&self) -> usize {
+        self.config.texts_per_call()
+    }
+
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        api::embed_batches(texts, &self.config, |batch| self.request(batch)).await
+    }
+}